@@ -0,0 +1,72 @@
+use super::{context::BorthItem, stack_view::BorthStackView};
+use std::{fmt::Display, rc::Rc};
+
+/// Forth backtrace of a failed execution: the words being called and the data stack
+pub struct BorthBacktrace<'a> {
+    frames: &'a [Rc<str>],
+    stack: &'a [BorthItem],
+    location: Option<&'a str>,
+}
+
+impl<'a> BorthBacktrace<'a> {
+    /// Create a new BorthBacktrace instance with the frames ordered outermost first
+    pub fn new(frames: &'a [Rc<str>], stack: &'a [BorthItem]) -> Self {
+        Self {
+            frames,
+            stack,
+            location: None,
+        }
+    }
+
+    /// Show where the error happened, as the source name and position
+    pub fn at(mut self, location: Option<&'a str>) -> Self {
+        self.location = location;
+        self
+    }
+}
+
+impl Display for BorthBacktrace<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.frames.is_empty() {
+            writeln!(f, "Backtrace:")?;
+            for frame in self.frames.iter().rev() {
+                writeln!(f, "  {}", frame)?;
+            }
+        }
+        if let Some(location) = self.location {
+            writeln!(f, "At: {}", location)?;
+        }
+        writeln!(f, "Stack: {}", BorthStackView::new(self.stack))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_innermost_frame_first() {
+        let frames: Vec<Rc<str>> = vec!["outer".into(), "inner".into()];
+        let backtrace = BorthBacktrace::new(&frames, &[1, 2]);
+        assert_eq!(
+            backtrace.to_string(),
+            "Backtrace:\n  inner\n  outer\nStack: <2> 1 2\n"
+        );
+    }
+
+    #[test]
+    fn test2_only_stack_at_top_level() {
+        let backtrace = BorthBacktrace::new(&[], &[]);
+        assert_eq!(backtrace.to_string(), "Stack: <0>\n");
+    }
+
+    #[test]
+    fn test3_location_before_stack() {
+        let frames: Vec<Rc<str>> = vec!["foo".into()];
+        let backtrace = BorthBacktrace::new(&frames, &[]).at(Some("lib.fth:2:3"));
+        assert_eq!(
+            backtrace.to_string(),
+            "Backtrace:\n  foo\nAt: lib.fth:2:3\nStack: <0>\n"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use super::{expression::BorthExpression, node::BorthNode};
+use std::{collections::HashMap, rc::Rc};
+
+/// Number of each word in a list, by the address of its expression
+pub type BorthWordIds = HashMap<*const BorthExpression, usize>;
+
+/// Return the words reachable from the given ones, each after the words it calls.
+/// A word called from several places is listed once.
+pub fn sorted_words(roots: impl Iterator<Item = Rc<BorthExpression>>) -> Vec<Rc<BorthExpression>> {
+    let mut words = vec![];
+    let mut seen = BorthWordIds::new();
+    let mut pending: Vec<_> = roots.map(|word| (word, false)).collect();
+    pending.reverse();
+    while let Some((word, expanded)) = pending.pop() {
+        if seen.contains_key(&Rc::as_ptr(&word)) {
+            continue;
+        }
+        if expanded {
+            seen.insert(Rc::as_ptr(&word), words.len());
+            words.push(word);
+            continue;
+        }
+        let mut callees = vec![];
+        if let BorthExpression::Word(_, body, _) = word.as_ref() {
+            push_callees(body, &mut callees);
+        }
+        pending.push((word, true));
+        pending.extend(callees.into_iter().rev().map(|callee| (callee, false)));
+    }
+    words
+}
+
+/// Push the words called by the nodes, including the ones in `if` bodies
+pub fn push_callees(nodes: &[BorthNode], callees: &mut Vec<Rc<BorthExpression>>) {
+    for node in nodes {
+        match node.expression() {
+            BorthExpression::Word(..) => callees.push(node.shared_expression()),
+            BorthExpression::IfElseThen(if_block, else_block) => {
+                push_callees(if_block, callees);
+                push_callees(else_block, callees);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Number the words in the order they are listed
+pub fn word_ids(words: &[Rc<BorthExpression>]) -> BorthWordIds {
+    words
+        .iter()
+        .enumerate()
+        .map(|(id, word)| (Rc::as_ptr(word), id))
+        .collect()
+}
+
+/// Return the number of the word, or 0 if it is not numbered
+pub fn word_id(ids: &BorthWordIds, word: &BorthExpression) -> usize {
+    ids.get(&std::ptr::from_ref(word))
+        .copied()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dict::BorthDict, parser};
+
+    fn create_roots(code: &str) -> Vec<Rc<BorthExpression>> {
+        let mut dict = BorthDict::new();
+        let tokens = parser::parse_tokens(code);
+        let nodes = parser::parse_expressions(tokens, &mut dict);
+        let mut roots = vec![];
+        push_callees(&nodes, &mut roots);
+        roots
+    }
+
+    fn names(words: &[Rc<BorthExpression>]) -> Vec<&str> {
+        words
+            .iter()
+            .filter_map(|word| match word.as_ref() {
+                BorthExpression::Word(name, ..) => Some(name.as_ref()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test1_list_callees_before_callers_once() {
+        let roots = create_roots(": a 1 ; : b a ; : c a b ; c b 1 if a then");
+        let words = sorted_words(roots.into_iter());
+        assert_eq!(names(&words), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test2_number_words_in_order() {
+        let words = sorted_words(create_roots(": a 1 ; : b a ; b").into_iter());
+        let ids = word_ids(&words);
+        assert_eq!(word_id(&ids, &words[1]), 1);
+    }
+}
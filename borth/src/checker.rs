@@ -0,0 +1,221 @@
+use super::{
+    callgraph, dict::builtin_name, effect::BorthEffect, errors::BorthError,
+    expression::BorthExpression, issue::BorthIssue, node::BorthNode, position::BorthPosition,
+};
+use std::{collections::HashMap, rc::Rc};
+
+/// Why the effect of some code is not known
+enum BorthUnknown {
+    /// It calls words from the host, fails or stops the program
+    Untracked,
+    /// The branches of the `if` at the position leave different depths
+    Inconsistent(BorthPosition),
+}
+
+type BorthInference = Result<BorthEffect, BorthUnknown>;
+
+/// Infer the stack effect of words and code without running them
+pub struct BorthChecker {
+    effects: HashMap<*const BorthExpression, Option<BorthEffect>>,
+}
+
+impl BorthChecker {
+    /// Create a new BorthChecker instance that knows no words
+    pub fn new() -> Self {
+        Self {
+            effects: HashMap::new(),
+        }
+    }
+
+    /// Infer the effect of the words, which must come after the words they call.
+    /// Return an issue for each word with branches that leave different depths.
+    pub fn infer_words(&mut self, words: &[Rc<BorthExpression>]) -> Vec<BorthIssue> {
+        let mut issues = vec![];
+        for word in words {
+            if let BorthExpression::Word(name, body, _) = word.as_ref() {
+                let inference = self.infer_nodes(body);
+                if let Err(BorthUnknown::Inconsistent(position)) = inference {
+                    let error = BorthError::InconsistentStackEffect;
+                    issues.push(BorthIssue::new(error, position, Some(name)));
+                }
+                self.effects.insert(Rc::as_ptr(word), inference.ok());
+            }
+        }
+        issues
+    }
+
+    /// Return the effect inferred for the word, if it is known
+    pub fn effect(&self, word: &BorthExpression) -> Option<BorthEffect> {
+        self.effects
+            .get(&std::ptr::from_ref(word))
+            .copied()
+            .flatten()
+    }
+
+    /// Return where the code, started with `depth` items on the stack, would
+    /// underflow, if it provably does. Code after an unknown effect is not checked.
+    pub fn check_code(&self, nodes: &[BorthNode], depth: usize) -> Option<BorthIssue> {
+        let mut effect = BorthEffect::new(0, depth);
+        for node in nodes {
+            effect = effect.then(self.infer_node(node).ok()?);
+            if effect.inputs > 0 {
+                let word = match node.expression() {
+                    BorthExpression::Word(name, ..) => Some(name.as_ref()),
+                    _ => None,
+                };
+                let error = BorthError::StackUnderflow;
+                return Some(BorthIssue::new(error, node.position(), word));
+            }
+        }
+        None
+    }
+
+    /// Return the effect of the code, if it is known
+    pub fn infer_code(&self, nodes: &[BorthNode]) -> Option<BorthEffect> {
+        self.infer_nodes(nodes).ok()
+    }
+
+    fn infer_nodes(&self, nodes: &[BorthNode]) -> BorthInference {
+        nodes
+            .iter()
+            .try_fold(BorthEffect::default(), |effect, node| {
+                Ok(effect.then(self.infer_node(node)?))
+            })
+    }
+
+    fn infer_node(&self, node: &BorthNode) -> BorthInference {
+        match node.expression() {
+            BorthExpression::Number(_) => Ok(BorthEffect::new(0, 1)),
+            BorthExpression::DotQuote(_) | BorthExpression::WordCreated => {
+                Ok(BorthEffect::default())
+            }
+            BorthExpression::Operation(operation) => {
+                builtin_effect(builtin_name(*operation).unwrap_or_default())
+                    .ok_or(BorthUnknown::Untracked)
+            }
+            BorthExpression::Word(..) => self
+                .effect(node.expression())
+                .ok_or(BorthUnknown::Untracked),
+            BorthExpression::IfElseThen(if_block, else_block) => {
+                self.infer_if(node.position(), if_block, else_block)
+            }
+            _ => Err(BorthUnknown::Untracked),
+        }
+    }
+
+    /// Infer the effect of taking the flag and running one of the branches,
+    /// which must leave the same depth
+    fn infer_if(
+        &self,
+        position: BorthPosition,
+        if_block: &[BorthNode],
+        else_block: &[BorthNode],
+    ) -> BorthInference {
+        let if_effect = self.infer_nodes(if_block)?;
+        let else_effect = self.infer_nodes(else_block)?;
+        if if_effect.depth_change() != else_effect.depth_change() {
+            return Err(BorthUnknown::Inconsistent(position));
+        }
+        let inputs = if_effect.inputs.max(else_effect.inputs);
+        let outputs = inputs.saturating_add_signed(if_effect.depth_change());
+        Ok(BorthEffect::new(1, 0).then(BorthEffect::new(inputs, outputs)))
+    }
+}
+
+/// Return the effect of a word body, inferring the words it calls first
+pub fn infer_body(body: &[BorthNode]) -> Option<BorthEffect> {
+    let mut callees = vec![];
+    callgraph::push_callees(body, &mut callees);
+    let mut checker = BorthChecker::new();
+    checker.infer_words(&callgraph::sorted_words(callees.into_iter()));
+    checker.infer_code(body)
+}
+
+/// Return the effect of a builtin word, or `None` if it stops the program
+fn builtin_effect(name: &str) -> Option<BorthEffect> {
+    let (inputs, outputs) = match name {
+        "+" | "-" | "*" | "/" | "=" | "<" | ">" | "and" | "or" => (2, 1),
+        "dup" => (1, 2),
+        "drop" | "." | "emit" => (1, 0),
+        "swap" => (2, 2),
+        "over" => (2, 3),
+        "rot" => (3, 3),
+        "not" => (1, 1),
+        "cr" => (0, 0),
+        _ => return None,
+    };
+    Some(BorthEffect::new(inputs, outputs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{callgraph, dict::BorthDict, parser};
+
+    fn check(code: &str) -> (Vec<BorthIssue>, Option<BorthIssue>, Option<BorthEffect>) {
+        let mut dict = BorthDict::new();
+        let nodes = parser::parse_expressions(parser::parse_tokens(code), &mut dict);
+        let roots = dict
+            .user_words()
+            .into_iter()
+            .map(|(_, word)| Rc::clone(word));
+        let words = callgraph::sorted_words(roots.collect::<Vec<_>>().into_iter());
+        let mut checker = BorthChecker::new();
+        let issues = checker.infer_words(&words);
+        let effect = dict.try_detect("foo").and_then(|foo| checker.effect(&foo));
+        (issues, checker.check_code(&nodes, 0), effect)
+    }
+
+    fn assert_effect(code: &str, inputs: usize, outputs: usize) {
+        let (_, _, effect) = check(code);
+        assert_eq!(effect, Some(BorthEffect::new(inputs, outputs)));
+    }
+
+    #[test]
+    fn test1_infer_builtins() {
+        assert_effect(": foo + ;", 2, 1);
+        assert_effect(": foo 1 2 ;", 0, 2);
+        assert_effect(": foo dup * swap drop ;", 2, 1);
+        assert_effect(": foo rot rot . ;", 3, 2);
+    }
+
+    #[test]
+    fn test2_infer_called_words() {
+        assert_effect(": sq dup * ; : foo sq sq + ;", 2, 1);
+    }
+
+    #[test]
+    fn test3_infer_branches_with_the_same_depth() {
+        assert_effect(": foo if 1 + else drop 0 then ;", 2, 1);
+        assert_effect(": foo 0 = if 1 + then ;", 2, 1);
+    }
+
+    #[test]
+    fn test4_report_branches_with_different_depths() {
+        let (issues, _, effect) = check(": foo 1 if 1 2 else 3 then ;");
+        let position = BorthPosition::new(8, 1, 9);
+        let error = BorthError::InconsistentStackEffect;
+        assert_eq!(issues, [BorthIssue::new(error, position, Some("foo"))]);
+        assert_eq!(effect, None);
+    }
+
+    #[test]
+    fn test5_unknown_effect_of_words_that_stop_the_program() {
+        let (issues, _, effect) = check(": foo 1 bye ;");
+        assert_eq!((issues, effect), (vec![], None));
+    }
+
+    #[test]
+    fn test6_report_underflow_in_the_code() {
+        let (_, underflow, _) = check(": foo + ; 1 2 foo foo");
+        let position = BorthPosition::new(18, 1, 19);
+        let issue = BorthIssue::new(BorthError::StackUnderflow, position, Some("foo"));
+        assert_eq!(underflow, Some(issue));
+    }
+
+    #[test]
+    fn test7_do_not_check_after_unknown_effects() {
+        let (_, underflow, _) = check("1 nope drop drop");
+        assert_eq!(underflow, None);
+    }
+}
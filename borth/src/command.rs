@@ -0,0 +1,30 @@
+/// What the runner does with the sources
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BorthCommand {
+    /// Evaluate them, the default
+    Run,
+    /// Translate them into a Rust program
+    Build,
+    /// Report the stack errors found without running them
+    Check,
+    /// Rewrite them in a uniform style
+    Fmt,
+    /// Serve editors over the standard input and output instead
+    Lsp,
+    /// Evaluate one of them step by step, with commands from the standard input
+    Debug,
+}
+
+impl BorthCommand {
+    /// Return the command named by the first argument, if it names one
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "build" => Some(BorthCommand::Build),
+            "check" => Some(BorthCommand::Check),
+            "fmt" => Some(BorthCommand::Fmt),
+            "lsp" => Some(BorthCommand::Lsp),
+            "debug" => Some(BorthCommand::Debug),
+            _ => None,
+        }
+    }
+}
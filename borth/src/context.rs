@@ -1,23 +1,46 @@
-use super::errors::*;
+use super::{
+    errors::*,
+    hook::BorthHook,
+    position::BorthPosition,
+    sink::{BorthSink, buffer::BorthBuffer},
+    stats::BorthStats,
+    step::BorthStep,
+};
+use std::rc::Rc;
 
 /// Each stack item takes 2 bytes
 pub type BorthItem = i16;
 
-/// Handle the stack and output of an interpreter execution
+/// Handle the stack, output and call frames of an interpreter execution
 pub struct BorthContext {
     capacity: usize,
     items: Vec<BorthItem>,
-    output: String,
+    output: Box<dyn BorthSink>,
+    last_char: Option<char>,
+    stats: BorthStats,
+    max_output: Option<usize>,
+    frames: Vec<Rc<str>>,
+    steps_left: Option<usize>,
+    error_position: Option<BorthPosition>,
+    hook: Option<Box<dyn BorthHook>>,
 }
 
 impl BorthContext {
-    /// Create a new BorthContext instance with the given stack size in bytes
+    /// Create a new BorthContext instance with the given stack size in bytes,
+    /// keeping the output in memory
     pub fn with_stack_size(stack_size: usize) -> Self {
         let capacity = stack_size / size_of::<BorthItem>();
         Self {
             capacity,
             items: Vec::with_capacity(capacity),
-            output: String::new(),
+            output: Box::new(BorthBuffer::default()),
+            last_char: None,
+            stats: BorthStats::default(),
+            max_output: None,
+            frames: vec![],
+            steps_left: None,
+            error_position: None,
+            hook: None,
         }
     }
 
@@ -34,9 +57,20 @@ impl BorthContext {
             return Err(BorthError::StackOverflow);
         }
         self.items.push(value);
+        self.stats.max_stack_depth = self.stats.max_stack_depth.max(self.items.len());
         Ok(())
     }
 
+    /// Return how many items fit in the stack
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Remove every item from the stack
+    pub(crate) fn clear_stack(&mut self) {
+        self.items.clear();
+    }
+
     /// Returns the items from the stack as as slice
     pub fn stack_items(&self) -> &[BorthItem] {
         self.items.as_slice()
@@ -44,37 +78,200 @@ impl BorthContext {
 
     // output
 
-    /// Push a string to the output buffer
-    pub fn print(&mut self, str: &str) {
-        if !self.output.is_empty() && !self.output.ends_with(char::is_whitespace) {
-            self.print_char(' ');
+    /// Replace the sink where the output is written
+    pub(crate) fn set_output(&mut self, output: Box<dyn BorthSink>) {
+        self.output = output;
+    }
+
+    /// Write a string to the output, separated by a space from the previous output
+    pub fn print(&mut self, str: &str) -> BorthResult<()> {
+        if self.last_char.is_some_and(|c| !c.is_whitespace()) {
+            self.print_char(' ')?;
         }
-        self.output.push_str(str);
+        self.print_raw(str)
     }
 
-    /// Push a character to the output buffer
-    pub fn print_char(&mut self, char: char) {
-        self.output.push(char);
+    /// Write a character to the output
+    pub fn print_char(&mut self, char: char) -> BorthResult<()> {
+        self.print_raw(char.encode_utf8(&mut [0; 4]))
     }
 
-    /// Return the output buffer as a str slice
+    /// Write a string to the output without separating it from the previous output.
+    /// Fail if the output would go over its limit.
+    pub fn print_raw(&mut self, str: &str) -> BorthResult<()> {
+        if self
+            .max_output
+            .is_some_and(|max| self.stats.output_bytes + str.len() > max)
+        {
+            return Err(BorthError::OutputLimitExceeded);
+        }
+        self.write(str)?;
+        self.stats.output_bytes += str.len();
+        Ok(())
+    }
+
+    /// Limit the total bytes of output written with `print`, or remove the limit with `None`
+    pub(crate) fn set_max_output(&mut self, max_output: Option<usize>) {
+        self.max_output = max_output;
+    }
+
+    /// Write a message from the interpreter, separated like `print` does
+    /// but not counted against the output limit
+    pub(crate) fn report(&mut self, message: &str) -> BorthResult<()> {
+        if self.last_char.is_some_and(|c| !c.is_whitespace()) {
+            self.write(" ")?;
+        }
+        self.write(message)
+    }
+
+    /// Write a string to the sink, not counted against the output limit
+    pub(crate) fn write(&mut self, str: &str) -> BorthResult<()> {
+        self.output.write_str(str)?;
+        if let Some(last) = str.chars().last() {
+            self.last_char = Some(last);
+        }
+        Ok(())
+    }
+
+    /// Write a new line to the output unless it is empty or already ends with one
+    pub(crate) fn start_line(&mut self) -> BorthResult<()> {
+        match self.last_char {
+            Some(last) if last != '\n' => self.write("\n"),
+            _ => Ok(()),
+        }
+    }
+
+    /// Flush any output not yet delivered by the sink
+    pub(crate) fn flush_output(&mut self) -> BorthResult<()> {
+        self.output.flush()
+    }
+
+    /// Take the output kept in memory, leaving it empty.
+    /// The next output is not separated by a space from the taken one.
+    pub(crate) fn take_output(&mut self) -> String {
+        self.last_char = None;
+        self.output.take()
+    }
+
+    /// Return the output kept in memory, empty if the sink writes it through
     pub fn output(&self) -> &str {
-        &self.output
+        self.output.contents()
+    }
+
+    /// Clear the stack, the call frames, the output kept in memory and the stats
+    pub(crate) fn reset(&mut self) {
+        self.stats = BorthStats::default();
+        self.clear_stack();
+        self.clear_error_location();
+        self.take_output();
+    }
+
+    // call frames
+
+    /// Push the name of the word being called
+    pub(crate) fn push_frame(&mut self, name: &Rc<str>) {
+        self.frames.push(Rc::clone(name));
+    }
+
+    /// Pop the name of the word that returned
+    pub(crate) fn pop_frame(&mut self) {
+        self.frames.pop();
+    }
+
+    /// Return the names of the words being called, outermost first.
+    /// After an error, the frames of the failing words are kept until cleared.
+    pub fn frames(&self) -> &[Rc<str>] {
+        &self.frames
+    }
+
+    // execution budget
+
+    /// Limit the number of expressions that can be evaluated, or remove the limit with `None`
+    pub fn set_max_steps(&mut self, max_steps: Option<usize>) {
+        self.steps_left = max_steps;
+    }
+
+    /// Return how many expressions can still be evaluated, if there is a limit
+    pub fn steps_left(&self) -> Option<usize> {
+        self.steps_left
+    }
+
+    /// Take one step from the budget or return an error if it is exhausted
+    pub(crate) fn consume_step(&mut self) -> BorthResult<()> {
+        match self.steps_left {
+            Some(0) => return Err(BorthError::BudgetExhausted),
+            Some(steps) => self.steps_left = Some(steps - 1),
+            None => {}
+        }
+        self.stats.steps += 1;
+        Ok(())
+    }
+
+    /// Return the counters of the evaluation so far
+    pub fn stats(&self) -> BorthStats {
+        self.stats
+    }
+
+    // hook
+
+    /// Set the hook called before each step, or remove it with `None`
+    pub(crate) fn set_hook(&mut self, hook: Option<Box<dyn BorthHook>>) {
+        self.hook = hook;
+    }
+
+    /// Show the step to the hook, if there is one, before it is taken
+    pub(crate) fn run_hook(&mut self, step: &BorthStep) -> BorthResult<()> {
+        let Some(mut hook) = self.hook.take() else {
+            return Ok(());
+        };
+        let result = hook.before_step(self, step);
+        self.hook = Some(hook);
+        result
+    }
+
+    /// Tell the hook, if there is one, that the run is over
+    pub(crate) fn finish_hook(&mut self) -> BorthResult<()> {
+        let Some(mut hook) = self.hook.take() else {
+            return Ok(());
+        };
+        let result = hook.after_run(self);
+        self.hook = Some(hook);
+        result
+    }
+
+    // diagnostics
+
+    /// Record the position of a failing expression, keeping the innermost one
+    pub(crate) fn locate_error(&mut self, position: BorthPosition) {
+        self.error_position.get_or_insert(position);
+    }
+
+    /// Return the position and innermost word recorded for the last error
+    pub(crate) fn error_location(&self) -> (Option<BorthPosition>, Option<&str>) {
+        (self.error_position, self.frames.last().map(Rc::as_ref))
+    }
+
+    /// Forget the position and call frames left by the last error
+    pub(crate) fn clear_error_location(&mut self) {
+        self.error_position = None;
+        self.frames.clear();
     }
 
     // testing
 
-    #[allow(dead_code)]
+    #[cfg(test)]
     /// Wrap test assertions to avoid code duplication
-    pub fn test(&self, stack: &[BorthItem], output: &str) {
+    pub(crate) fn test(&self, stack: &[BorthItem], output: &str) {
         assert_eq!(self.stack_items(), stack);
-        assert_eq!(self.output, output);
+        assert_eq!(self.output(), output);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sink::writer::BorthWriter;
+    use std::{fs::File, io::Cursor};
 
     fn create_context() -> BorthContext {
         BorthContext::with_stack_size(10)
@@ -124,31 +321,31 @@ mod tests {
     #[test]
     fn test06_print_once() {
         let mut ctx = create_context();
-        ctx.print(&"hello");
+        assert_eq!(ctx.print("hello"), Ok(()));
         ctx.test(&[], "hello");
     }
 
     #[test]
     fn test07_print_many() {
         let mut ctx = create_context();
-        ctx.print(&"hello");
-        ctx.print(&"world");
+        let _ = ctx.print("hello");
+        let _ = ctx.print("world");
         ctx.test(&[], "hello world");
     }
 
     #[test]
     fn test08_print_many_with_new_line() {
         let mut ctx = create_context();
-        ctx.print(&"hello");
-        ctx.print_char('\n');
-        ctx.print(&"world");
+        let _ = ctx.print("hello");
+        let _ = ctx.print_char('\n');
+        let _ = ctx.print("world");
         ctx.test(&[], "hello\nworld");
     }
 
     #[test]
     fn test09_output_slice() {
         let mut ctx = create_context();
-        ctx.print(&"hello world");
+        let _ = ctx.print("hello world");
         assert_eq!(ctx.output(), "hello world");
     }
 
@@ -160,4 +357,112 @@ mod tests {
         }
         assert_eq!(ctx.stack_items(), &[1, 2, 3, 4]);
     }
+
+    #[test]
+    fn test11_start_line() {
+        let mut ctx = create_context();
+        let _ = ctx.start_line();
+        let _ = ctx.print("hello");
+        let _ = ctx.start_line();
+        let _ = ctx.start_line();
+        let _ = ctx.print("world");
+        ctx.test(&[], "hello\nworld");
+    }
+
+    #[test]
+    fn test12_keep_innermost_error_location() {
+        let mut ctx = create_context();
+        ctx.push_frame(&"outer".into());
+        ctx.push_frame(&"inner".into());
+        ctx.locate_error(BorthPosition::new(4, 1, 5));
+        ctx.locate_error(BorthPosition::new(0, 1, 1));
+        let (position, word) = ctx.error_location();
+        assert_eq!(position, Some(BorthPosition::new(4, 1, 5)));
+        assert_eq!(word, Some("inner"));
+        ctx.clear_error_location();
+        assert_eq!(ctx.error_location(), (None, None));
+    }
+
+    #[test]
+    fn test13_push_and_pop_frames() {
+        let mut ctx = create_context();
+        ctx.push_frame(&"foo".into());
+        ctx.push_frame(&"bar".into());
+        ctx.pop_frame();
+        assert_eq!(ctx.frames(), &["foo".into()]);
+    }
+
+    #[test]
+    fn test14_consume_steps() {
+        let mut ctx = create_context();
+        assert_eq!(ctx.consume_step(), Ok(()));
+        ctx.set_max_steps(Some(2));
+        assert_eq!(ctx.consume_step(), Ok(()));
+        assert_eq!(ctx.steps_left(), Some(1));
+        assert_eq!(ctx.consume_step(), Ok(()));
+        assert_eq!(ctx.consume_step(), Err(BorthError::BudgetExhausted));
+        assert_eq!(ctx.steps_left(), Some(0));
+        assert_eq!(ctx.stats().steps, 3);
+    }
+
+    #[test]
+    fn test15_stats() {
+        let mut ctx = create_context();
+        let _ = ctx.push_value(1);
+        let _ = ctx.push_value(2);
+        let _ = ctx.pop_value();
+        let _ = ctx.print("12");
+        let expected = BorthStats {
+            steps: 0,
+            max_stack_depth: 2,
+            output_bytes: 2,
+        };
+        assert_eq!(ctx.stats(), expected);
+        ctx.reset();
+        assert_eq!(ctx.stats(), BorthStats::default());
+    }
+
+    #[test]
+    fn test16_spacing_with_write_through_sink() {
+        let path = "/tmp/borth-test-sink.txt";
+        let mut ctx = create_context();
+        let file = File::create(path);
+        assert!(file.is_ok());
+        if let Ok(file) = file {
+            ctx.set_output(Box::new(BorthWriter::new(file)));
+        }
+        let _ = ctx.print("hello");
+        let _ = ctx.print_char('\n');
+        let _ = ctx.print("big");
+        let _ = ctx.print("world");
+        assert_eq!(ctx.output(), "");
+        let written = std::fs::read_to_string(path).ok();
+        assert_eq!(written.as_deref(), Some("hello\nbig world"));
+    }
+
+    #[test]
+    fn test17_can_not_print() {
+        let mut ctx = create_context();
+        ctx.set_output(Box::new(BorthWriter::new(Cursor::new([]))));
+        assert_eq!(ctx.print("hello"), Err(BorthError::CanNotWriteToOutput));
+    }
+
+    #[test]
+    fn test18_take_output() {
+        let mut ctx = create_context();
+        let _ = ctx.print("hello");
+        assert_eq!(ctx.take_output(), "hello");
+        let _ = ctx.print("world");
+        ctx.test(&[], "world");
+    }
+
+    #[test]
+    fn test19_output_limit() {
+        let mut ctx = create_context();
+        ctx.set_max_output(Some(7));
+        assert_eq!(ctx.print("hello"), Ok(()));
+        assert_eq!(ctx.print("world"), Err(BorthError::OutputLimitExceeded));
+        assert_eq!(ctx.report("error"), Ok(()));
+        ctx.test(&[], "hello error");
+    }
 }
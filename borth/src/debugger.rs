@@ -0,0 +1,279 @@
+mod breakpoint;
+mod mode;
+
+use borth::{BorthContext, BorthError, BorthHook, BorthResult, BorthStackView, BorthStep};
+use breakpoint::BorthBreakpoint;
+use mode::BorthStepMode;
+use std::io::{BufRead, Write};
+
+const PROMPT: &str = "(borth) ";
+const RECENT_LINES: usize = 10;
+const LISTED_LINES: usize = 2;
+const HELP: &str = "\
+step, s             Take the next step, going into the words called (default)
+next, n             Take steps until the next one in the current word
+finish, f           Take steps until the current word returns
+continue, c         Take steps until a breakpoint
+break, b LINE|WORD  Stop on entering the line or calling the word
+break, b            List the breakpoints
+delete, d LINE|WORD Remove a breakpoint
+stack               Show the data stack
+frames              Show the return stack, the innermost word last
+output              Show the last lines of output
+list, l             Show the lines around the current step
+quit, q             Stop the program
+";
+
+/// Interactive debugger that stops before steps of the evaluation of a source,
+/// shows where it is and reads commands until one resumes it
+pub struct BorthDebugger<R, W> {
+    reader: R,
+    writer: W,
+    source: String,
+    lines: Vec<String>,
+    breakpoints: Vec<BorthBreakpoint>,
+    mode: BorthStepMode,
+    line: usize,
+    lines_by_depth: Vec<usize>,
+    shown_output: usize,
+}
+
+impl<R: BufRead, W: Write> BorthDebugger<R, W> {
+    /// Create a new BorthDebugger instance that stops at the first step of the code,
+    /// reading commands from the reader and writing to the writer
+    pub fn new(reader: R, writer: W, source: &str, code: &str) -> Self {
+        Self {
+            reader,
+            writer,
+            source: source.to_string(),
+            lines: code.lines().map(str::to_string).collect(),
+            breakpoints: vec![],
+            mode: BorthStepMode::Step,
+            line: 0,
+            lines_by_depth: vec![],
+            shown_output: 0,
+        }
+    }
+
+    /// Read commands until one resumes the evaluation. At the end of the commands,
+    /// run to the end without stopping.
+    fn prompt(&mut self, ctx: &BorthContext) -> BorthResult<()> {
+        loop {
+            self.write(PROMPT)?;
+            let mut command = String::new();
+            if !matches!(self.reader.read_line(&mut command), Ok(1..)) {
+                self.mode = BorthStepMode::Continue;
+                self.breakpoints.clear();
+                return self.write("\n");
+            }
+            if let Some(mode) = self.run_command(ctx, command.trim())? {
+                self.mode = mode;
+                return Ok(());
+            }
+        }
+    }
+
+    /// Run a command and return how to resume, if it resumes the evaluation
+    fn run_command(
+        &mut self,
+        ctx: &BorthContext,
+        command: &str,
+    ) -> BorthResult<Option<BorthStepMode>> {
+        let (name, arg) = command.split_once(' ').unwrap_or((command, ""));
+        let depth = ctx.frames().len();
+        match name {
+            "" | "s" | "step" => return Ok(Some(BorthStepMode::Step)),
+            "n" | "next" => return Ok(Some(BorthStepMode::Next(depth))),
+            "f" | "finish" => return Ok(Some(BorthStepMode::Finish(depth))),
+            "c" | "continue" => return Ok(Some(BorthStepMode::Continue)),
+            "q" | "quit" => return Err(BorthError::Bye(0)),
+            "b" | "break" => self.add_breakpoint(arg.trim())?,
+            "d" | "delete" => self.delete_breakpoint(arg.trim())?,
+            "stack" => self.write(&format!("{}\n", BorthStackView::new(ctx.stack_items())))?,
+            "frames" => self.show_frames(ctx)?,
+            "output" => self.show_recent_output(ctx)?,
+            "l" | "list" => self.show_lines()?,
+            "h" | "help" => self.write(HELP)?,
+            _ => self.write(&format!("unknown command {}, try help\n", name))?,
+        }
+        Ok(None)
+    }
+
+    fn add_breakpoint(&mut self, arg: &str) -> BorthResult<()> {
+        if arg.is_empty() {
+            let list: Vec<String> = self.breakpoints.iter().map(|b| b.to_string()).collect();
+            return self.write(&format!("breakpoints: {}\n", list.join(", ")));
+        }
+        let breakpoint = BorthBreakpoint::parse(arg);
+        self.write(&format!("breakpoint on {}\n", breakpoint))?;
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+        Ok(())
+    }
+
+    fn delete_breakpoint(&mut self, arg: &str) -> BorthResult<()> {
+        let breakpoint = BorthBreakpoint::parse(arg);
+        let len = self.breakpoints.len();
+        self.breakpoints.retain(|b| *b != breakpoint);
+        match self.breakpoints.len() < len {
+            true => self.write(&format!("deleted breakpoint on {}\n", breakpoint)),
+            false => self.write(&format!("no breakpoint on {}\n", breakpoint)),
+        }
+    }
+
+    /// Write the output produced since the last stop, and where the step is
+    fn show_step(&mut self, ctx: &BorthContext, step: &BorthStep) -> BorthResult<()> {
+        self.show_new_output(ctx)?;
+        let position = step.position();
+        let location = format!("{}:{} {}\n", self.source, position, step.describe());
+        self.write(&location)?;
+        self.show_line(position.line, "")
+    }
+
+    fn show_lines(&mut self) -> BorthResult<()> {
+        let first = self.line.saturating_sub(LISTED_LINES).max(1);
+        for line in first..=self.line + LISTED_LINES {
+            let marker = if line == self.line { "->" } else { "" };
+            self.show_line(line, marker)?;
+        }
+        Ok(())
+    }
+
+    fn show_line(&mut self, line: usize, marker: &str) -> BorthResult<()> {
+        let Some(text) = line.checked_sub(1).and_then(|i| self.lines.get(i)) else {
+            return Ok(());
+        };
+        let text = format!("{:>2}{:>4} | {}\n", marker, line, text);
+        self.write(&text)
+    }
+
+    fn show_frames(&mut self, ctx: &BorthContext) -> BorthResult<()> {
+        let frames: Vec<&str> = ctx.frames().iter().map(|name| name.as_ref()).collect();
+        self.write(&format!("<{}> {}\n", frames.len(), frames.join(" ")))
+    }
+
+    fn show_recent_output(&mut self, ctx: &BorthContext) -> BorthResult<()> {
+        let lines: Vec<&str> = ctx.output().lines().collect();
+        let recent = &lines[lines.len().saturating_sub(RECENT_LINES)..];
+        for line in recent {
+            self.write(&format!("{}\n", line))?;
+        }
+        Ok(())
+    }
+
+    /// Write the output produced since it was last shown, ending it with a new line
+    fn show_new_output(&mut self, ctx: &BorthContext) -> BorthResult<()> {
+        let output = ctx.output().get(self.shown_output..).unwrap_or_default();
+        self.shown_output = ctx.output().len();
+        if output.is_empty() {
+            return Ok(());
+        }
+        self.write(output)?;
+        match output.ends_with('\n') {
+            true => Ok(()),
+            false => self.write("\n"),
+        }
+    }
+
+    /// Record the line of a step taken with `depth` words being called, and return
+    /// whether it is a new line for that call, so coming back from a word defined
+    /// elsewhere does not enter the line of the caller again
+    fn enter_line(&mut self, depth: usize, line: usize) -> bool {
+        self.line = line;
+        self.lines_by_depth.resize(depth + 1, 0);
+        let Some(last) = self.lines_by_depth.get_mut(depth) else {
+            return false;
+        };
+        let entered = *last != line;
+        *last = line;
+        entered
+    }
+
+    fn write(&mut self, text: &str) -> BorthResult<()> {
+        self.writer
+            .write_all(text.as_bytes())
+            .and_then(|_| self.writer.flush())
+            .or(Err(BorthError::CanNotWriteToOutput))
+    }
+}
+
+impl<R: BufRead, W: Write> BorthHook for BorthDebugger<R, W> {
+    /// Stop at the step if the mode or a breakpoint says so
+    fn before_step(&mut self, ctx: &BorthContext, step: &BorthStep) -> BorthResult<()> {
+        let entered_line = self.enter_line(ctx.frames().len(), step.position().line);
+        let hit = self.breakpoints.iter().any(|b| b.hits(step, entered_line));
+        if !hit && !self.mode.stops(ctx.frames().len()) {
+            return Ok(());
+        }
+        self.show_step(ctx, step)?;
+        self.prompt(ctx)
+    }
+
+    /// Write the output left to show and the final stack
+    fn after_run(&mut self, ctx: &BorthContext) -> BorthResult<()> {
+        self.show_new_output(ctx)?;
+        let stack = BorthStackView::new(ctx.stack_items());
+        self.write(&format!("finished with stack {}\n", stack))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared_writer::SharedWriter;
+    use borth::BorthInterpreter;
+    use std::io::Cursor;
+
+    const CODE: &str = ": sq dup * ;\n3 sq .\n4 sq .";
+
+    fn debug(commands: &str) -> (BorthInterpreter, String) {
+        let (writer, written) = SharedWriter::new();
+        let reader = Cursor::new(commands.to_string());
+        let debugger = BorthDebugger::new(reader, writer, "main.fth", CODE);
+        let mut interpreter = BorthInterpreter::new();
+        interpreter.set_hook(Some(Box::new(debugger)));
+        interpreter.run_code(CODE);
+        let transcript = String::from_utf8_lossy(&written.borrow()).to_string();
+        (interpreter, transcript)
+    }
+
+    #[test]
+    fn test1_step_into_and_over_words() {
+        let (_, transcript) = debug("s\ns\ns\ns\nframes\nstack\nn\nn\nc\n");
+        let expected = concat!(
+            "main.fth:1:1 :\n     1 | : sq dup * ;\n(borth) ",
+            "main.fth:2:1 3\n     2 | 3 sq .\n(borth) ",
+            "main.fth:2:3 sq\n     2 | 3 sq .\n(borth) ",
+            "main.fth:1:6 dup\n     1 | : sq dup * ;\n(borth) ",
+            "main.fth:1:10 *\n     1 | : sq dup * ;\n(borth) ",
+            "<1> sq\n(borth) <2> 3 3\n(borth) ",
+            "main.fth:2:6 .\n     2 | 3 sq .\n(borth) ",
+            "9\nmain.fth:3:1 4\n     3 | 4 sq .\n(borth) ",
+            " 16\nfinished with stack <0>\n",
+        );
+        assert_eq!(transcript, expected);
+    }
+
+    #[test]
+    fn test2_break_on_words_and_lines() {
+        let (_, transcript) = debug("b sq\nb 3\nc\nc\nc\nd sq\nl\nc\n");
+        assert!(transcript.contains("breakpoint on word sq\n(borth) breakpoint on line 3\n"));
+        assert!(transcript.contains("(borth) main.fth:2:3 sq\n"));
+        assert!(transcript.contains("(borth) 9\nmain.fth:3:1 4\n"));
+        assert!(transcript.contains("(borth) main.fth:3:3 sq\n"));
+        assert!(transcript.contains("deleted breakpoint on word sq\n(borth)      1 |"));
+        assert!(transcript.contains("->   3 | 4 sq .\n(borth)  16\nfinished"));
+    }
+
+    #[test]
+    fn test3_quit_and_end_of_commands() {
+        let (interpreter, transcript) = debug("output\nq\n");
+        assert_eq!(interpreter.last_error(), Some(&BorthError::Bye(0)));
+        assert!(transcript.ends_with("(borth) (borth) finished with stack <0>\n"));
+
+        let (interpreter, transcript) = debug("");
+        assert_eq!(interpreter.last_error(), None);
+        assert!(transcript.ends_with("(borth) \n9 16\nfinished with stack <0>\n"));
+    }
+}
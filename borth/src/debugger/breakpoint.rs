@@ -0,0 +1,55 @@
+use borth::BorthStep;
+use std::fmt::Display;
+
+/// Where the debugger stops the evaluation
+#[derive(Debug, PartialEq)]
+pub enum BorthBreakpoint {
+    /// On entering a line of the source
+    Line(usize),
+    /// On calling a word
+    Word(String),
+}
+
+impl BorthBreakpoint {
+    /// Return the breakpoint on the line, if the argument is a number, or on the word
+    pub fn parse(arg: &str) -> Self {
+        match arg.parse() {
+            Ok(line) => BorthBreakpoint::Line(line),
+            Err(_) => BorthBreakpoint::Word(arg.to_lowercase()),
+        }
+    }
+
+    /// Whether the step hits the breakpoint, given if it enters a new line
+    pub fn hits(&self, step: &BorthStep, entered_line: bool) -> bool {
+        match self {
+            BorthBreakpoint::Line(line) => entered_line && step.position().line == *line,
+            BorthBreakpoint::Word(word) => {
+                step.word().is_some_and(|w| w.eq_ignore_ascii_case(word))
+            }
+        }
+    }
+}
+
+impl Display for BorthBreakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BorthBreakpoint::Line(line) => write!(f, "line {}", line),
+            BorthBreakpoint::Word(word) => write!(f, "word {}", word),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_parse_lines_and_words() {
+        assert_eq!(BorthBreakpoint::parse("12"), BorthBreakpoint::Line(12));
+        assert_eq!(
+            BorthBreakpoint::parse("Square"),
+            BorthBreakpoint::Word("square".into())
+        );
+        assert_eq!(BorthBreakpoint::parse("-1").to_string(), "word -1");
+    }
+}
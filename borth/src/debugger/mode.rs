@@ -0,0 +1,39 @@
+/// When the debugger stops next, besides the breakpoints
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BorthStepMode {
+    /// At the next step, going into the words called
+    Step,
+    /// At the next step with at most the given number of words being called
+    Next(usize),
+    /// At the next step with less than the given number of words being called
+    Finish(usize),
+    /// Only at breakpoints
+    Continue,
+}
+
+impl BorthStepMode {
+    /// Whether to stop at a step taken with `depth` words being called
+    pub fn stops(&self, depth: usize) -> bool {
+        match self {
+            BorthStepMode::Step => true,
+            BorthStepMode::Next(max) => depth <= *max,
+            BorthStepMode::Finish(max) => depth < *max,
+            BorthStepMode::Continue => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_stop_by_depth() {
+        assert!(BorthStepMode::Step.stops(3));
+        assert!(BorthStepMode::Next(1).stops(1));
+        assert!(!BorthStepMode::Next(1).stops(2));
+        assert!(!BorthStepMode::Finish(1).stops(1));
+        assert!(BorthStepMode::Finish(1).stops(0));
+        assert!(!BorthStepMode::Continue.stops(0));
+    }
+}
@@ -0,0 +1,34 @@
+use super::position::BorthPosition;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Where a word was defined with `:` in the code given to the interpreter
+pub struct BorthDefinition {
+    /// Position of the name of the word
+    pub position: BorthPosition,
+    /// Byte offset right after the `;` that ends the definition
+    pub end: usize,
+}
+
+impl BorthDefinition {
+    /// Create a new BorthDefinition instance
+    pub fn new(position: BorthPosition, end: usize) -> Self {
+        Self { position, end }
+    }
+
+    /// Return the definition in the code, from the name to the `;`
+    pub fn source<'a>(&self, code: &'a str) -> Option<&'a str> {
+        code.get(self.position.offset..self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_source_from_name_to_end() {
+        let definition = BorthDefinition::new(BorthPosition::new(4, 1, 5), 13);
+        assert_eq!(definition.source("1 : foo dup ; foo"), Some("foo dup ;"));
+        assert_eq!(definition.source("short"), None);
+    }
+}
@@ -0,0 +1,68 @@
+use super::{errors::BorthError, position::BorthPosition};
+use std::fmt::Display;
+
+/// Verbose description of an error, with the source name, position and failing word
+pub struct BorthDiagnostic<'a> {
+    source: &'a str,
+    error: &'a BorthError,
+    position: Option<BorthPosition>,
+    word: Option<&'a str>,
+}
+
+impl<'a> BorthDiagnostic<'a> {
+    /// Create a new BorthDiagnostic instance
+    pub fn new(
+        source: &'a str,
+        error: &'a BorthError,
+        position: Option<BorthPosition>,
+        word: Option<&'a str>,
+    ) -> Self {
+        Self {
+            source,
+            error,
+            position,
+            word,
+        }
+    }
+}
+
+impl Display for BorthDiagnostic<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:", self.source)?;
+        if let Some(position) = self.position {
+            write!(f, "{}:", position)?;
+        }
+        match self.error {
+            BorthError::UnknownWord(word) => write!(f, " unknown-word {}", word)?,
+            error => write!(f, " {}", error)?,
+        }
+        if let Some(word) = self.word {
+            write!(f, " in word {}", word)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_display_position_and_word() {
+        let error = BorthError::StackUnderflow;
+        let position = Some(BorthPosition::new(20, 3, 7));
+        let diagnostic = BorthDiagnostic::new("main.fth", &error, position, Some("FOO"));
+        assert_eq!(
+            diagnostic.to_string(),
+            "main.fth:3:7: stack-underflow in word FOO"
+        );
+    }
+
+    #[test]
+    fn test2_display_unknown_word_by_name() {
+        let error = BorthError::UnknownWord("bar".into());
+        let position = Some(BorthPosition::new(0, 1, 1));
+        let diagnostic = BorthDiagnostic::new("main.fth", &error, position, None);
+        assert_eq!(diagnostic.to_string(), "main.fth:1:1: unknown-word bar");
+    }
+}
@@ -1,84 +1,284 @@
 use super::{
+    checker,
     context::BorthItem,
-    expression::{arithmetic::*, booleans::*, output::*, specials::*, stack::*, *},
+    definition::BorthDefinition,
+    effect::BorthEffect,
+    errors::*,
+    expression::{arithmetic::*, booleans::*, output::*, specials::*, stack::*, system::*, *},
+    limits::BorthLimits,
+    native::BorthNative,
+    node::BorthNode,
     parser::BorthIterator,
+    sandbox::BorthSandbox,
+    vm::code::BorthCode,
 };
 use std::{collections::HashMap, rc::Rc};
 
-/// Store words and their definitions
+/// Builtin words implemented by an operation
+pub const BUILTINS: [(&str, BorthOperation); 20] = [
+    ("+", add::call),
+    ("-", sub::call),
+    ("*", mul::call),
+    ("/", div::call),
+    ("dup", dup::call),
+    ("drop", drop::call),
+    ("swap", swap::call),
+    ("over", over::call),
+    ("rot", rot::call),
+    ("=", eq::call),
+    ("<", lt::call),
+    (">", gt::call),
+    ("and", and::call),
+    ("or", or::call),
+    ("not", not::call),
+    (".", dot::call),
+    ("emit", emit::call),
+    ("cr", cr::call),
+    ("bye", bye::call),
+    ("bye-status", bye_status::call),
+];
+
+/// Return the name of a builtin word by its operation
+pub fn builtin_name(operation: BorthOperation) -> Option<&'static str> {
+    BUILTINS
+        .iter()
+        .find(|(_, builtin)| std::ptr::fn_addr_eq(*builtin, operation))
+        .map(|(name, _)| *name)
+}
+
+/// Store words and their definitions, and how much the definitions take
 pub struct BorthDict {
     words: HashMap<String, Rc<BorthExpression>>,
+    locations: HashMap<String, BorthDefinition>,
     word_created: Rc<BorthExpression>,
+    limits: BorthLimits,
+    sandbox: BorthSandbox,
+    optimize: bool,
+    strict: bool,
+    definitions: usize,
+    code_size: usize,
 }
 
 impl BorthDict {
     /// Create a new BorthDict instance with builtin words
+    #[cfg(test)]
     pub fn new() -> Self {
+        Self::with_sandbox(BorthSandbox::default())
+    }
+
+    /// Create a new BorthDict instance with builtin words, where the ones
+    /// denied by the sandbox fail when called
+    pub fn with_sandbox(sandbox: BorthSandbox) -> Self {
         let mut this = Self {
             words: HashMap::new(),
+            locations: HashMap::new(),
             word_created: Rc::new(BorthExpression::WordCreated),
+            limits: BorthLimits::default(),
+            sandbox,
+            optimize: false,
+            strict: false,
+            definitions: 0,
+            code_size: 0,
         };
         this.init_words();
         this
     }
 
+    /// Optimize the code of the words defined from now on
+    pub fn enable_optimizer(&mut self) {
+        self.optimize = true;
+    }
+
+    /// Check the stack effects declared by the words defined from now on
+    pub fn enable_strict(&mut self) {
+        self.strict = true;
+    }
+
+    /// Set the caps on the number of definitions and their total size
+    pub fn set_limits(&mut self, limits: BorthLimits) {
+        self.limits = limits;
+    }
+
     // initialization
 
     fn init_words(&mut self) {
-        self.add("+", BorthExpression::Operation(add::call));
-        self.add("-", BorthExpression::Operation(sub::call));
-        self.add("*", BorthExpression::Operation(mul::call));
-        self.add("/", BorthExpression::Operation(div::call));
-        self.add("dup", BorthExpression::Operation(dup::call));
-        self.add("drop", BorthExpression::Operation(drop::call));
-        self.add("swap", BorthExpression::Operation(swap::call));
-        self.add("over", BorthExpression::Operation(over::call));
-        self.add("rot", BorthExpression::Operation(rot::call));
-        self.add("=", BorthExpression::Operation(eq::call));
-        self.add("<", BorthExpression::Operation(lt::call));
-        self.add(">", BorthExpression::Operation(gt::call));
-        self.add("and", BorthExpression::Operation(and::call));
-        self.add("or", BorthExpression::Operation(or::call));
-        self.add("not", BorthExpression::Operation(not::call));
-        self.add(".", BorthExpression::Operation(dot::call));
-        self.add("emit", BorthExpression::Operation(emit::call));
-        self.add("cr", BorthExpression::Operation(cr::call));
+        for (name, operation) in BUILTINS {
+            self.add(name, BorthExpression::Operation(operation));
+        }
+        for word in self.sandbox.denied_words() {
+            if self.words.contains_key(word) {
+                self.add(word, BorthExpression::ForbiddenWord);
+            }
+        }
     }
 
     // word definition
 
     fn add(&mut self, token: &str, exp: BorthExpression) {
-        self.words.insert(token.to_lowercase(), Rc::new(exp));
+        self.insert(token, Rc::new(exp));
     }
 
-    /// Add a new word to the dictionary
-    pub fn add_word(&mut self, token: &str, body: Vec<Rc<BorthExpression>>) -> Rc<BorthExpression> {
-        self.add(token, BorthExpression::Word(body));
+    /// Add a new word to the dictionary, unless it goes over the limits.
+    /// In strict mode, a declared effect is compared with the inferred one and the
+    /// word is not added if they differ. If the effect can not be inferred, it is
+    /// checked each time the word returns.
+    pub fn add_word(
+        &mut self,
+        token: &str,
+        body: Vec<BorthNode>,
+        declared: Option<BorthEffect>,
+    ) -> Rc<BorthExpression> {
+        let declared = declared.filter(|_| self.strict);
+        let inferred = declared.and_then(|_| checker::infer_body(&body));
+        if let Some((declared, inferred)) = declared.zip(inferred)
+            && !declared.allows(inferred)
+        {
+            return Rc::new(BorthExpression::StackEffectMismatch(token.to_string()));
+        }
+        let body_size: usize = body.iter().map(|node| node.expression().size()).sum();
+        match self.reserve(1, body_size) {
+            Err(BorthError::WordLimitExceeded) => {
+                return Rc::new(BorthExpression::WordLimitExceeded);
+            }
+            Err(_) => return Rc::new(BorthExpression::CodeLimitExceeded),
+            Ok(()) => {}
+        }
+        let word = self.compile_word(token, body, declared.filter(|_| inferred.is_none()));
+        self.add(token, word);
         Rc::clone(&self.word_created)
     }
 
+    /// Count new definitions of the given total size, unless they go over the limits
+    pub fn reserve(&mut self, definitions: usize, size: usize) -> BorthResult<()> {
+        let definitions = self.definitions + definitions;
+        if self.limits.max_words.is_some_and(|max| definitions > max) {
+            return Err(BorthError::WordLimitExceeded);
+        }
+        let code_size = self.code_size + size;
+        if self.limits.max_code.is_some_and(|max| code_size > max) {
+            return Err(BorthError::CodeLimitExceeded);
+        }
+        self.definitions = definitions;
+        self.code_size = code_size;
+        Ok(())
+    }
+
+    /// Create a Word expression, optimized if the optimizer is enabled, that checks
+    /// the given effect at runtime, if any
+    pub fn compile_word(
+        &self,
+        name: &str,
+        body: Vec<BorthNode>,
+        effect: Option<BorthEffect>,
+    ) -> BorthExpression {
+        BorthExpression::compiled_word(name, body, self.optimize, effect)
+    }
+
+    /// Make the name refer to the given expression
+    pub fn insert(&mut self, name: &str, expression: Rc<BorthExpression>) {
+        let name = name.to_lowercase();
+        self.locations.remove(&name);
+        self.words.insert(name, expression);
+    }
+
+    /// Remember where the current definition of the word is in the code
+    pub fn locate(&mut self, name: &str, definition: BorthDefinition) {
+        self.locations.insert(name.to_lowercase(), definition);
+    }
+
+    /// Return where the current definition of the word is, if it was defined with `:`
+    /// in the code given to this dictionary
+    pub fn definition(&self, name: &str) -> Option<BorthDefinition> {
+        self.locations.get(&name.to_lowercase()).copied()
+    }
+
+    /// Return the expression of a builtin word by name, or `ForbiddenWord` if
+    /// the sandbox denies it
+    pub fn builtin(&self, name: &str) -> Option<BorthExpression> {
+        let (_, operation) = BUILTINS.iter().find(|(builtin, _)| *builtin == name)?;
+        match self.sandbox.denies(name) {
+            true => Some(BorthExpression::ForbiddenWord),
+            false => Some(BorthExpression::Operation(*operation)),
+        }
+    }
+
+    /// Return a DotQuote expression printing the text, or `ForbiddenWord` if the
+    /// sandbox denies `."`
+    pub fn dot_quote(&self, text: String) -> BorthExpression {
+        match self.sandbox.denies(".\"") {
+            true => BorthExpression::ForbiddenWord,
+            false => BorthExpression::DotQuote(text),
+        }
+    }
+
+    /// Return the words defined with `:` and their names, sorted by name
+    pub fn user_words(&self) -> Vec<(&str, &Rc<BorthExpression>)> {
+        let mut words: Vec<_> = self
+            .words
+            .iter()
+            .filter(|(_, word)| matches!(word.as_ref(), BorthExpression::Word(..)))
+            .map(|(name, word)| (name.as_str(), word))
+            .collect();
+        words.sort_by_key(|(name, _)| *name);
+        words
+    }
+
+    /// Add a word implemented by the host application, or return an error
+    /// if the name is not a valid word
+    pub fn register_native(&mut self, name: &str, native: BorthNative) -> BorthResult<()> {
+        if name.is_empty()
+            || name.contains(char::is_whitespace)
+            || name.parse::<BorthItem>().is_ok()
+        {
+            return Err(BorthError::InvalidWord);
+        }
+        self.add(name, BorthExpression::Native(native));
+        Ok(())
+    }
+
+    /// Return the names of the defined words, sorted
+    pub fn words(&self) -> Vec<&str> {
+        let mut words: Vec<&str> = self.words.keys().map(String::as_str).collect();
+        words.sort();
+        words
+    }
+
     // evaluation
 
-    /// Detect the next expression in the iterator
-    pub fn detect_next(&mut self, iterator: &mut BorthIterator) -> Option<Rc<BorthExpression>> {
-        while let Some((word, _)) = iterator.next() {
-            if word.is_empty() {
-                continue;
+    /// Detect the next expression in the iterator, skipping `(` comments unless a word
+    /// named `(` was defined
+    pub fn detect_next(&mut self, iterator: &mut BorthIterator) -> Option<BorthNode> {
+        loop {
+            let token = iterator.find(|token| !token.word.is_empty())?;
+            if token.word != "(" || self.words.contains_key("(") {
+                let expression = self.detect_expression(token.word, iterator);
+                return Some(BorthNode::new(token.position, expression));
             }
-
-            let expression = self.try_detect(word);
-            if expression.is_some() {
-                return expression;
+            if comment::skip(iterator).is_none() {
+                let expression = Rc::new(BorthExpression::IncompleteStatement);
+                return Some(BorthNode::new(token.position, expression));
             }
+        }
+    }
 
-            return match word.to_lowercase().as_str() {
-                ".\"" => Some(Rc::new(dot_quote::create(iterator))),
-                "if" => Some(Rc::new(if_else_then::create(iterator, self))),
-                ":" => Some(word_def::create(iterator, self)),
-                _ => Some(Rc::new(BorthExpression::UnknownWord(word.to_string()))),
-            };
+    fn detect_expression(
+        &mut self,
+        word: &str,
+        iterator: &mut BorthIterator,
+    ) -> Rc<BorthExpression> {
+        if let Some(expression) = self.try_detect(word) {
+            return expression;
+        }
+        match word.to_lowercase().as_str() {
+            ".\"" if self.sandbox.denies(word) => {
+                dot_quote::create(iterator);
+                Rc::new(BorthExpression::ForbiddenWord)
+            }
+            ".\"" => Rc::new(dot_quote::create(iterator)),
+            "if" => Rc::new(if_else_then::create(iterator, self)),
+            ":" => word_def::create(iterator, self),
+            _ => Rc::new(BorthExpression::UnknownWord(word.to_string())),
         }
-        None
     }
 
     /// Try to detect a word and return its expression
@@ -94,6 +294,24 @@ impl BorthDict {
     }
 }
 
+/// Drop the definitions one at a time, since words hold the words they call
+/// and dropping a long chain of them recursively would overflow the native stack
+impl Drop for BorthDict {
+    fn drop(&mut self) {
+        let mut expressions: Vec<Rc<BorthExpression>> =
+            self.words.drain().map(|(_, word)| word).collect();
+        let mut codes: Vec<Rc<BorthCode>> = vec![];
+        while !expressions.is_empty() || !codes.is_empty() {
+            if let Some(expression) = expressions.pop().and_then(|e| Rc::try_unwrap(e).ok()) {
+                expression.release(&mut expressions, &mut codes);
+            }
+            if let Some(code) = codes.pop().and_then(|c| Rc::try_unwrap(c).ok()) {
+                code.release(&mut expressions, &mut codes);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -107,7 +325,7 @@ mod tests {
         let mut dict = create_dict();
         let tokens = parser::parse_tokens(code);
         let result = dict.detect_next(&mut tokens.iter());
-        assert!(matches!(result, Some(actual) if actual.as_ref() == expected));
+        assert!(matches!(result, Some(actual) if actual.expression() == expected));
     }
 
     fn assert_unknown_word(token: &str) {
@@ -231,11 +449,17 @@ mod tests {
         assert_detect("if else then", &BorthExpression::IfElseThen(vec![], vec![]));
         assert_detect(
             "if 1 then",
-            &BorthExpression::IfElseThen(vec![Rc::new(BorthExpression::Number(1))], vec![]),
+            &BorthExpression::IfElseThen(
+                vec![BorthNode::unlocated(BorthExpression::Number(1))],
+                vec![],
+            ),
         );
         assert_detect(
             "if else 1 then",
-            &BorthExpression::IfElseThen(vec![], vec![Rc::new(BorthExpression::Number(1))]),
+            &BorthExpression::IfElseThen(
+                vec![],
+                vec![BorthNode::unlocated(BorthExpression::Number(1))],
+            ),
         );
     }
 
@@ -248,17 +472,32 @@ mod tests {
     }
 
     #[test]
-    fn test_case_insensitive() {
-        let body = vec![
-            Rc::new(BorthExpression::Number(1)),
-            Rc::new(BorthExpression::Number(9)),
-            Rc::new(BorthExpression::Operation(add::call)),
-            Rc::new(BorthExpression::Number(5)),
-        ];
+    fn test_words_sorted() {
         let mut dict = create_dict();
-        dict.add_word("foo", body.clone());
-        assert!(
-            matches!(dict.try_detect("FoO"), Some(actual) if actual.as_ref() == &BorthExpression::Word(body))
+        dict.add_word(
+            "Foo",
+            vec![BorthNode::unlocated(BorthExpression::Number(1))],
+            None,
         );
+        let words = dict.words();
+        assert_eq!(words.len(), 21);
+        assert!(words.is_sorted());
+        assert!(words.contains(&"foo"));
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let body = || {
+            vec![
+                BorthNode::unlocated(BorthExpression::Number(1)),
+                BorthNode::unlocated(BorthExpression::Number(9)),
+                BorthNode::unlocated(BorthExpression::Operation(add::call)),
+                BorthNode::unlocated(BorthExpression::Number(5)),
+            ]
+        };
+        let mut dict = create_dict();
+        dict.add_word("foo", body(), None);
+        let expected = BorthExpression::word("foo", body());
+        assert!(matches!(dict.try_detect("FoO"), Some(actual) if actual.as_ref() == &expected));
     }
 }
@@ -0,0 +1,75 @@
+use std::fmt::Display;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+/// Stack effect of some code: the items it takes from the stack and the ones it leaves
+pub struct BorthEffect {
+    /// Items taken from the stack the code starts with
+    pub inputs: usize,
+    /// Items left in their place
+    pub outputs: usize,
+}
+
+impl BorthEffect {
+    /// Create a new BorthEffect instance
+    pub fn new(inputs: usize, outputs: usize) -> Self {
+        Self { inputs, outputs }
+    }
+
+    /// Return the effect of running this code and then the next one.
+    /// Inputs the next code takes beyond this code's outputs become inputs of both.
+    pub fn then(self, next: BorthEffect) -> Self {
+        let missing = next.inputs.saturating_sub(self.outputs);
+        Self {
+            inputs: self.inputs + missing,
+            outputs: self.outputs + missing - next.inputs + next.outputs,
+        }
+    }
+
+    /// Whether code with the given effect fits this declared one: it changes the depth
+    /// of the stack in the same way, without taking more items than declared
+    pub fn allows(&self, effect: BorthEffect) -> bool {
+        effect.inputs <= self.inputs && effect.depth_change() == self.depth_change()
+    }
+
+    /// Return how many items the code adds to the stack, negative if it removes them
+    pub fn depth_change(&self) -> isize {
+        self.outputs as isize - self.inputs as isize
+    }
+}
+
+impl Display for BorthEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "( {} -- {} )", self.inputs, self.outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_then_uses_outputs_as_inputs() {
+        let effect = BorthEffect::new(0, 2).then(BorthEffect::new(2, 1));
+        assert_eq!(effect, BorthEffect::new(0, 1));
+    }
+
+    #[test]
+    fn test2_then_takes_missing_inputs_from_the_start() {
+        let effect = BorthEffect::new(1, 1).then(BorthEffect::new(3, 2));
+        assert_eq!(effect, BorthEffect::new(3, 2));
+        assert_eq!(effect.depth_change(), -1);
+    }
+
+    #[test]
+    fn test3_allow_effects_that_take_fewer_items() {
+        let declared = BorthEffect::new(2, 3);
+        assert!(declared.allows(BorthEffect::new(0, 1)));
+        assert!(!declared.allows(BorthEffect::new(0, 2)));
+        assert!(!declared.allows(BorthEffect::new(3, 4)));
+    }
+
+    #[test]
+    fn test4_display_like_a_stack_comment() {
+        assert_eq!(BorthEffect::new(2, 1).to_string(), "( 2 -- 1 )");
+    }
+}
@@ -1,9 +1,12 @@
+use crate::context::BorthItem;
 use std::fmt::Display;
 
 pub type BorthResult<T> = Result<T, BorthError>;
 
 #[derive(Debug, PartialEq)]
-/// Posible errors from a Borth execution
+/// Posible errors from a Borth execution.
+/// Its `Display` gives the kebab-case name of the error, followed by the argument
+/// for argument errors and the word for stack effect mismatches, or `?` for unknown words.
 pub enum BorthError {
     // Common errors:
     StackUnderflow,
@@ -13,15 +16,29 @@ pub enum BorthError {
     UnknownWord(String),
 
     // Custom errors:
-    MissingArguments,
     TooManyArguments,
-    BadArguments,
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidValue(String),
     CanNotReadFile,
     CanNotReadCode,
     CanNotWriteFile,
+    InvalidStackFile,
+    StackItemOutOfRange,
+    InvalidImage,
+    InvalidMessage,
+    ImageCellWidthMismatch,
+    InconsistentStackEffect,
+    StackEffectMismatch(String),
     CanNotWriteToOutput,
     IncompleteStatement,
     RuntimeError,
+    BudgetExhausted,
+    OutputLimitExceeded,
+    WordLimitExceeded,
+    CodeLimitExceeded,
+    ForbiddenWord,
+    Bye(BorthItem),
 }
 
 impl Display for BorthError {
@@ -29,15 +46,24 @@ impl Display for BorthError {
         if f.alternate() {
             write!(f, "{:?}", self)
         } else {
-            let name = match self {
-                Self::UnknownWord(_) => "?",
-                _ => &to_kebabcase(format!("{:#?}", self)),
-            };
-            f.write_str(name)
+            let debug = format!("{:?}", self);
+            let name = to_kebabcase(debug.split('(').next().unwrap_or_default().to_string());
+            match self {
+                Self::UnknownWord(_) => f.write_str("?"),
+                Self::UnknownOption(arg)
+                | Self::MissingValue(arg)
+                | Self::InvalidValue(arg)
+                | Self::StackEffectMismatch(arg) => {
+                    write!(f, "{} {}", name, arg)
+                }
+                _ => f.write_str(&name),
+            }
         }
     }
 }
 
+impl std::error::Error for BorthError {}
+
 fn to_kebabcase(str: String) -> String {
     str.chars()
         .map(|c| {
@@ -65,4 +91,15 @@ mod tests {
     fn test2_display_unknown_word_as_question_mark() {
         assert_eq!(BorthError::UnknownWord("sth".into()).to_string(), "?");
     }
+
+    #[test]
+    fn test3_display_budget_exhausted() {
+        assert_eq!(BorthError::BudgetExhausted.to_string(), "budget-exhausted");
+    }
+
+    #[test]
+    fn test4_display_argument_errors_with_argument() {
+        let error = BorthError::InvalidValue("--stack-size=big".into());
+        assert_eq!(error.to_string(), "invalid-value --stack-size=big");
+    }
 }
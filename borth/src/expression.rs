@@ -3,40 +3,143 @@ pub mod booleans;
 pub mod output;
 pub mod specials;
 pub mod stack;
+pub mod system;
 
-use super::{context::*, errors::*};
+use super::{
+    context::*,
+    effect::BorthEffect,
+    errors::*,
+    native::BorthNative,
+    node::BorthNode,
+    position::BorthPosition,
+    step::BorthStep,
+    vm::{self, code::BorthCode, compiler, optimizer},
+};
 use specials::*;
 use std::rc::Rc;
 
+/// Builtin operation on the context
+pub type BorthOperation = fn(&mut BorthContext) -> BorthResult<()>;
+
+#[allow(unpredictable_function_pointer_comparisons)]
 #[derive(Debug, PartialEq)]
 /// Posible expressions in Borth
 pub enum BorthExpression {
     Number(BorthItem),
-    Operation(fn(&mut BorthContext) -> BorthResult<()>),
+    Operation(BorthOperation),
+    Native(BorthNative),
     DotQuote(String),
-    IfElseThen(Vec<Rc<Self>>, Vec<Rc<Self>>),
-    Word(Vec<Rc<Self>>),
+    IfElseThen(Vec<BorthNode>, Vec<BorthNode>),
+    Word(Rc<str>, Vec<BorthNode>, Rc<BorthCode>),
     UnknownWord(String),
     IncompleteStatement,
     InvalidWord,
+    ForbiddenWord,
+    WordLimitExceeded,
+    CodeLimitExceeded,
+    StackEffectMismatch(String),
     WordCreated,
 }
 
 impl BorthExpression {
-    /// Handle the evaluation of the expression
-    pub fn eval(&self, ctx: &mut BorthContext) -> BorthResult<()> {
+    /// Create a Word expression, compiling its body
+    #[cfg(test)]
+    pub fn word(name: &str, body: Vec<BorthNode>) -> Self {
+        let code = compiler::compile(&body);
+        BorthExpression::Word(name.into(), body, Rc::new(code))
+    }
+
+    /// Create a Word expression, compiling its body, optimizing the code if asked
+    /// and checking at runtime that it has the given effect, if any
+    pub fn compiled_word(
+        name: &str,
+        body: Vec<BorthNode>,
+        optimize: bool,
+        effect: Option<BorthEffect>,
+    ) -> Self {
+        let mut code = compiler::compile(&body);
+        if optimize {
+            code = optimizer::optimize(&code);
+        }
+        code.set_effect(effect);
+        BorthExpression::Word(name.into(), body, Rc::new(code))
+    }
+
+    /// Handle the evaluation of the expression found at the position, showing it
+    /// to the hook first and taking one step from the budget
+    pub fn eval(&self, ctx: &mut BorthContext, position: BorthPosition) -> BorthResult<()> {
+        ctx.run_hook(&BorthStep::expression(position, self))?;
+        ctx.consume_step()?;
         match self {
             BorthExpression::Number(value) => ctx.push_value(*value),
             BorthExpression::Operation(cb) => cb(ctx),
+            BorthExpression::Native(native) => native.call(ctx),
             BorthExpression::DotQuote(str) => dot_quote::call(ctx, str),
             BorthExpression::IfElseThen(if_block, else_block) => {
                 if_else_then::call(ctx, if_block, else_block)
             }
-            BorthExpression::Word(body) => word_def::call(ctx, body),
+            BorthExpression::Word(name, _, code) => vm::call(ctx, name, code),
             BorthExpression::UnknownWord(word) => Err(BorthError::UnknownWord(word.into())),
             BorthExpression::IncompleteStatement => Err(BorthError::IncompleteStatement),
             BorthExpression::InvalidWord => Err(BorthError::InvalidWord),
+            BorthExpression::ForbiddenWord => Err(BorthError::ForbiddenWord),
+            BorthExpression::WordLimitExceeded => Err(BorthError::WordLimitExceeded),
+            BorthExpression::CodeLimitExceeded => Err(BorthError::CodeLimitExceeded),
+            BorthExpression::StackEffectMismatch(word) => {
+                Err(BorthError::StackEffectMismatch(word.into()))
+            }
             BorthExpression::WordCreated => Ok(()),
         }
     }
+
+    /// Return the error the parser left in this expression, if it is one
+    pub fn parse_error(&self) -> Option<BorthError> {
+        match self {
+            BorthExpression::UnknownWord(word) => Some(BorthError::UnknownWord(word.into())),
+            BorthExpression::IncompleteStatement => Some(BorthError::IncompleteStatement),
+            BorthExpression::InvalidWord => Some(BorthError::InvalidWord),
+            BorthExpression::ForbiddenWord => Some(BorthError::ForbiddenWord),
+            BorthExpression::WordLimitExceeded => Some(BorthError::WordLimitExceeded),
+            BorthExpression::CodeLimitExceeded => Some(BorthError::CodeLimitExceeded),
+            BorthExpression::StackEffectMismatch(word) => {
+                Some(BorthError::StackEffectMismatch(word.into()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Count this expression and the ones nested in its blocks, without expanding words
+    pub fn size(&self) -> usize {
+        match self {
+            BorthExpression::IfElseThen(if_block, else_block) => {
+                1 + if_block
+                    .iter()
+                    .chain(else_block)
+                    .map(|node| node.expression().size())
+                    .sum::<usize>()
+            }
+            _ => 1,
+        }
+    }
+
+    /// Move the shared expressions and code this expression holds to the given lists,
+    /// so they can be dropped one at a time instead of recursively
+    pub fn release(
+        self,
+        expressions: &mut Vec<Rc<BorthExpression>>,
+        codes: &mut Vec<Rc<BorthCode>>,
+    ) {
+        let nodes = match self {
+            BorthExpression::Word(_, body, code) => {
+                codes.push(code);
+                body
+            }
+            BorthExpression::IfElseThen(mut if_block, else_block) => {
+                if_block.extend(else_block);
+                if_block
+            }
+            _ => return,
+        };
+        expressions.extend(nodes.into_iter().map(BorthNode::into_expression));
+    }
 }
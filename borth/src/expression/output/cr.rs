@@ -2,8 +2,7 @@ use crate::{context::*, errors::BorthResult};
 
 /// Print a new line.
 pub fn call(ctx: &mut BorthContext) -> BorthResult<()> {
-    ctx.print_char('\n');
-    Ok(())
+    ctx.print_char('\n')
 }
 
 #[cfg(test)]
@@ -3,8 +3,7 @@ use crate::{context::*, errors::*};
 /// Print the top item of the stack.
 pub fn call(ctx: &mut BorthContext) -> BorthResult<()> {
     let item1 = ctx.pop_value()?;
-    ctx.print(&item1.to_string());
-    Ok(())
+    ctx.print(&item1.to_string())
 }
 
 #[cfg(test)]
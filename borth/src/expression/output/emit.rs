@@ -4,8 +4,7 @@ use crate::{context::*, errors::*};
 pub fn call(ctx: &mut BorthContext) -> BorthResult<()> {
     let item1 = ctx.pop_value()?;
     let ascii = char::from_u32(item1 as u32).ok_or(BorthError::RuntimeError)?;
-    ctx.print(&ascii.to_string());
-    Ok(())
+    ctx.print(&ascii.to_string())
 }
 
 #[cfg(test)]
@@ -1,3 +1,4 @@
+pub mod comment;
 pub mod dot_quote;
 pub mod if_else_then;
 pub mod word_def;
@@ -0,0 +1,69 @@
+use crate::{effect::BorthEffect, parser::BorthIterator};
+
+/// Skip a `(` comment up to the word that ends with `)`.
+/// Return the words inside, or `None` if the comment is not closed.
+pub fn skip<'a>(iterator: &mut BorthIterator<'a>) -> Option<Vec<&'a str>> {
+    let mut words = vec![];
+    for token in iterator.by_ref() {
+        let (word, closed) = match token.word.strip_suffix(')') {
+            Some(word) => (word, true),
+            None => (token.word, false),
+        };
+        if !word.is_empty() {
+            words.push(word);
+        }
+        if closed {
+            return Some(words);
+        }
+    }
+    None
+}
+
+/// Return the stack effect declared by the words of a comment like `( a b -- c )`,
+/// or `None` if they do not have exactly one `--`
+pub fn declared_effect(words: &[&str]) -> Option<BorthEffect> {
+    let separator = words.iter().position(|word| *word == "--")?;
+    let (inputs, outputs) = words.split_at(separator);
+    let outputs = outputs.get(1..)?;
+    if outputs.contains(&"--") {
+        return None;
+    }
+    Some(BorthEffect::new(inputs.len(), outputs.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_tokens;
+
+    fn assert_comment(code: &str, expected: Option<Vec<&str>>, rest: usize) {
+        let tokens = parse_tokens(code);
+        let mut iterator = tokens.iter();
+        iterator.next();
+        assert_eq!(skip(&mut iterator), expected);
+        assert_eq!(iterator.count(), rest);
+    }
+
+    #[test]
+    fn test1_skip_up_to_the_closing_word() {
+        assert_comment("( a b -- c ) 1", Some(vec!["a", "b", "--", "c"]), 1);
+        assert_comment("( n --  n) dup", Some(vec!["n", "--", "n"]), 1);
+        assert_comment("(  ) dup", Some(vec![]), 1);
+    }
+
+    #[test]
+    fn test2_comment_not_closed() {
+        assert_comment("( a b", None, 0);
+    }
+
+    #[test]
+    fn test3_declared_effect() {
+        assert_eq!(
+            declared_effect(&["a", "b", "--", "c"]),
+            Some(BorthEffect::new(2, 1))
+        );
+        assert_eq!(declared_effect(&["--"]), Some(BorthEffect::new(0, 0)));
+        assert_eq!(declared_effect(&["just", "a", "note"]), None);
+        assert_eq!(declared_effect(&["a", "--", "b", "--"]), None);
+    }
+}
@@ -3,21 +3,20 @@ use crate::{context::*, errors::BorthResult, expression::BorthExpression, parser
 /// Create a DotQuote expression.
 pub fn create(iterator: &mut BorthIterator) -> BorthExpression {
     let mut str = String::new();
-    for (token, whitespace) in iterator.by_ref() {
-        if token.ends_with("\"") {
-            str.push_str(token.trim_end_matches("\""));
+    for token in iterator.by_ref() {
+        if token.word.ends_with("\"") {
+            str.push_str(token.word.trim_end_matches("\""));
             return BorthExpression::DotQuote(str);
         }
-        str.push_str(token);
-        str.push_str(whitespace);
+        str.push_str(token.word);
+        str.push_str(token.whitespace);
     }
     BorthExpression::IncompleteStatement
 }
 
 /// Print string from expression.
 pub fn call(ctx: &mut BorthContext, str: &str) -> BorthResult<()> {
-    ctx.print(str);
-    Ok(())
+    ctx.print(str)
 }
 
 #[cfg(test)]
@@ -28,7 +27,7 @@ mod tests {
         BorthContext::with_stack_size(10)
     }
 
-    fn assert_create_and_call(ctx: &mut BorthContext, tokens: Vec<(&str, &str)>, output: &str) {
+    fn assert_create_and_call(ctx: &mut BorthContext, tokens: BorthTokens, output: &str) {
         assert!(match create(&mut tokens.iter()) {
             BorthExpression::DotQuote(str) => call(ctx, &str).is_ok(),
             _ => false,
@@ -1,5 +1,6 @@
-use crate::{context::*, dict::BorthDict, errors::*, expression::BorthExpression, parser::*};
-use std::rc::Rc;
+use crate::{
+    context::*, dict::BorthDict, errors::*, expression::BorthExpression, node::BorthNode, parser::*,
+};
 
 /// Create an IfElseThen expression.
 pub fn create(iterator: &mut BorthIterator, dict: &mut BorthDict) -> BorthExpression {
@@ -8,7 +9,7 @@ pub fn create(iterator: &mut BorthIterator, dict: &mut BorthDict) -> BorthExpres
 
     let mut in_else_block = false;
     while let Some(exp) = dict.detect_next(iterator) {
-        if let BorthExpression::UnknownWord(word) = exp.as_ref() {
+        if let BorthExpression::UnknownWord(word) = exp.expression() {
             match word.to_lowercase().as_str() {
                 "then" => return BorthExpression::IfElseThen(if_block, else_block),
                 "else" => {
@@ -30,8 +31,8 @@ pub fn create(iterator: &mut BorthIterator, dict: &mut BorthDict) -> BorthExpres
 /// Call the IfElseThen expression.
 pub fn call(
     ctx: &mut BorthContext,
-    if_block: &Vec<Rc<BorthExpression>>,
-    else_block: &Vec<Rc<BorthExpression>>,
+    if_block: &Vec<BorthNode>,
+    else_block: &Vec<BorthNode>,
 ) -> BorthResult<()> {
     let block_to_eval = if ctx.pop_value()? != 0 {
         if_block
@@ -56,7 +57,7 @@ mod tests {
         BorthDict::new()
     }
 
-    fn assert_create_and_call(ctx: &mut BorthContext, tokens: Vec<(&str, &str)>) {
+    fn assert_create_and_call(ctx: &mut BorthContext, tokens: BorthTokens) {
         let mut dict = create_dict();
         assert!(match create(&mut tokens.iter(), &mut dict) {
             BorthExpression::IfElseThen(if_block, else_block) =>
@@ -65,7 +66,7 @@ mod tests {
         });
     }
 
-    fn assert_incomplete_statement(tokens: Vec<(&str, &str)>) {
+    fn assert_incomplete_statement(tokens: BorthTokens) {
         let mut dict = create_dict();
         assert_eq!(
             create(&mut tokens.iter(), &mut dict),
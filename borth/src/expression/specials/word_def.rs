@@ -1,45 +1,73 @@
-use crate::{context::*, dict::BorthDict, errors::*, expression::BorthExpression, parser::*};
+use crate::{
+    context::*,
+    definition::BorthDefinition,
+    dict::BorthDict,
+    effect::BorthEffect,
+    expression::{BorthExpression, specials::comment},
+    node::BorthNode,
+    parser::*,
+};
 use std::rc::Rc;
 
-/// Create a Word expression.
+/// Create a Word expression, or `IncompleteStatement` if there is no `;` to end it.
 pub fn create(iterator: &mut BorthIterator, dict: &mut BorthDict) -> Rc<BorthExpression> {
     match iterator.next() {
         None => Rc::new(BorthExpression::InvalidWord),
-        Some((word, _)) => {
-            if word.parse::<BorthItem>().is_ok() {
+        Some(token) => {
+            if token.word.parse::<BorthItem>().is_ok() {
                 return Rc::new(BorthExpression::InvalidWord);
             }
 
-            let mut body = vec![];
-            while let Some(exp) = dict.detect_next(iterator) {
-                if let BorthExpression::UnknownWord(word) = exp.as_ref() {
-                    if word == ";" {
-                        break;
-                    }
-                }
-                body.push(exp);
-            }
+            let declared = declared_effect(iterator);
+            let Some((body, end)) = read_body(iterator, dict) else {
+                return Rc::new(BorthExpression::IncompleteStatement);
+            };
             if body.is_empty() {
                 return Rc::new(BorthExpression::InvalidWord);
             }
-            dict.add_word(word, body)
+            let word = dict.add_word(token.word, body, declared);
+            if let BorthExpression::WordCreated = word.as_ref() {
+                dict.locate(token.word, BorthDefinition::new(token.position, end));
+            }
+            word
         }
     }
 }
 
-/// Call the Word expression.
-pub fn call(ctx: &mut BorthContext, body: &Vec<Rc<BorthExpression>>) -> BorthResult<()> {
-    for exp in body {
-        exp.eval(ctx)?;
+/// Read the expressions up to the `;`, and return them with the offset right after it
+fn read_body(
+    iterator: &mut BorthIterator,
+    dict: &mut BorthDict,
+) -> Option<(Vec<BorthNode>, usize)> {
+    let mut body = vec![];
+    while let Some(exp) = dict.detect_next(iterator) {
+        if let BorthExpression::UnknownWord(word) = exp.expression()
+            && word == ";"
+        {
+            return Some((body, exp.position().offset + word.len()));
+        }
+        body.push(exp);
     }
-    Ok(())
+    None
+}
+
+/// Take the `( a b -- c )` comment right after the name, if there is one,
+/// and return the effect it declares
+fn declared_effect(iterator: &mut BorthIterator) -> Option<BorthEffect> {
+    let mut lookahead = iterator.clone();
+    lookahead
+        .find(|token| !token.word.is_empty())
+        .filter(|token| token.word == "(")?;
+    let words = comment::skip(&mut lookahead)?;
+    *iterator = lookahead;
+    comment::declared_effect(&words)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::expression::{BorthExpression, arithmetic::mul, booleans::eq, stack::dup};
-    use std::rc::Rc;
+    use crate::position::BorthPosition;
 
     fn create_dict() -> BorthDict {
         BorthDict::new()
@@ -67,7 +95,10 @@ mod tests {
             "foo",
             "foo 1 ;",
             &mut dict,
-            &BorthExpression::Word(vec![Rc::new(BorthExpression::Number(1))]),
+            &BorthExpression::word(
+                "foo",
+                vec![BorthNode::unlocated(BorthExpression::Number(1))],
+            ),
         );
     }
 
@@ -91,10 +122,13 @@ mod tests {
             "dup-twice",
             "dup-twice dup dup ;",
             &mut dict,
-            &BorthExpression::Word(vec![
-                Rc::new(BorthExpression::Operation(dup::call)),
-                Rc::new(BorthExpression::Operation(dup::call)),
-            ]),
+            &BorthExpression::word(
+                "dup-twice",
+                vec![
+                    BorthNode::unlocated(BorthExpression::Operation(dup::call)),
+                    BorthNode::unlocated(BorthExpression::Operation(dup::call)),
+                ],
+            ),
         );
     }
 
@@ -105,11 +139,14 @@ mod tests {
             "countup",
             "countup 1 2 3 ;",
             &mut dict,
-            &BorthExpression::Word(vec![
-                Rc::new(BorthExpression::Number(1)),
-                Rc::new(BorthExpression::Number(2)),
-                Rc::new(BorthExpression::Number(3)),
-            ]),
+            &BorthExpression::word(
+                "countup",
+                vec![
+                    BorthNode::unlocated(BorthExpression::Number(1)),
+                    BorthNode::unlocated(BorthExpression::Number(2)),
+                    BorthNode::unlocated(BorthExpression::Number(3)),
+                ],
+            ),
         );
     }
 
@@ -120,16 +157,22 @@ mod tests {
             "foo",
             "foo dup ;",
             &mut dict,
-            &BorthExpression::Word(vec![Rc::new(BorthExpression::Operation(dup::call))]),
+            &BorthExpression::word(
+                "foo",
+                vec![BorthNode::unlocated(BorthExpression::Operation(dup::call))],
+            ),
         );
         assert_create_word(
             "foo",
             "foo dup dup ;",
             &mut dict,
-            &BorthExpression::Word(vec![
-                Rc::new(BorthExpression::Operation(dup::call)),
-                Rc::new(BorthExpression::Operation(dup::call)),
-            ]),
+            &BorthExpression::word(
+                "foo",
+                vec![
+                    BorthNode::unlocated(BorthExpression::Operation(dup::call)),
+                    BorthNode::unlocated(BorthExpression::Operation(dup::call)),
+                ],
+            ),
         );
     }
 
@@ -140,7 +183,10 @@ mod tests {
             "swap",
             "swap dup ;",
             &mut dict,
-            &BorthExpression::Word(vec![Rc::new(BorthExpression::Operation(dup::call))]),
+            &BorthExpression::word(
+                "swap",
+                vec![BorthNode::unlocated(BorthExpression::Operation(dup::call))],
+            ),
         );
     }
 
@@ -151,8 +197,56 @@ mod tests {
             "+",
             "+ * ;",
             &mut dict,
-            &BorthExpression::Word(vec![Rc::new(BorthExpression::Operation(mul::call))]),
+            &BorthExpression::word(
+                "+",
+                vec![BorthNode::unlocated(BorthExpression::Operation(mul::call))],
+            ),
+        );
+    }
+
+    #[test]
+    fn test9_declared_effect_is_not_part_of_the_body() {
+        let mut dict = create_dict();
+        dict.enable_strict();
+        assert_create_word(
+            "foo",
+            "foo ( -- n ) 1 ;",
+            &mut dict,
+            &BorthExpression::word(
+                "foo",
+                vec![BorthNode::unlocated(BorthExpression::Number(1))],
+            ),
+        );
+        assert_create(
+            "bar ( n -- n ) 1 ;",
+            &mut dict,
+            &BorthExpression::StackEffectMismatch("bar".into()),
+        );
+    }
+
+    #[test]
+    fn test10_incomplete_without_end() {
+        let mut dict = create_dict();
+        assert_create("foo 1 2", &mut dict, &BorthExpression::IncompleteStatement);
+        assert!(dict.try_detect("foo").is_none());
+    }
+
+    #[test]
+    fn test11_locate_definition() {
+        let mut dict = create_dict();
+        assert_create(
+            "foo ( -- n ) 1 ; 2",
+            &mut dict,
+            &BorthExpression::WordCreated,
+        );
+        let position = BorthPosition::new(0, 1, 1);
+        assert_eq!(
+            dict.definition("FOO"),
+            Some(BorthDefinition::new(position, 16))
         );
+        assert_create("bar 1 ;", &mut dict, &BorthExpression::WordCreated);
+        dict.insert("bar", Rc::new(BorthExpression::Number(2)));
+        assert_eq!(dict.definition("bar"), None);
     }
 
     #[test]
@@ -162,9 +256,12 @@ mod tests {
             "hello",
             "hello .\" hello world\" ;",
             &mut dict,
-            &BorthExpression::Word(vec![Rc::new(BorthExpression::DotQuote(
-                "hello world".into(),
-            ))]),
+            &BorthExpression::word(
+                "hello",
+                vec![BorthNode::unlocated(BorthExpression::DotQuote(
+                    "hello world".into(),
+                ))],
+            ),
         );
     }
 
@@ -175,14 +272,17 @@ mod tests {
             "is-zero",
             "is-zero 0 = if -1 else 0 then ;",
             &mut dict,
-            &BorthExpression::Word(vec![
-                Rc::new(BorthExpression::Number(0)),
-                Rc::new(BorthExpression::Operation(eq::call)),
-                Rc::new(BorthExpression::IfElseThen(
-                    vec![Rc::new(BorthExpression::Number(-1))],
-                    vec![Rc::new(BorthExpression::Number(0))],
-                )),
-            ]),
+            &BorthExpression::word(
+                "is-zero",
+                vec![
+                    BorthNode::unlocated(BorthExpression::Number(0)),
+                    BorthNode::unlocated(BorthExpression::Operation(eq::call)),
+                    BorthNode::unlocated(BorthExpression::IfElseThen(
+                        vec![BorthNode::unlocated(BorthExpression::Number(-1))],
+                        vec![BorthNode::unlocated(BorthExpression::Number(0))],
+                    )),
+                ],
+            ),
         );
     }
 }
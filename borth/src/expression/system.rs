@@ -0,0 +1,2 @@
+pub mod bye;
+pub mod bye_status;
@@ -0,0 +1,31 @@
+use crate::{context::*, errors::*};
+
+/// Stop the program with exit status 0, leaving the stack as it is.
+pub fn call(_ctx: &mut BorthContext) -> BorthResult<()> {
+    Err(BorthError::Bye(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_context() -> BorthContext {
+        BorthContext::with_stack_size(10)
+    }
+
+    #[test]
+    fn test1_bye_without_status() {
+        let mut ctx = create_context();
+        assert_eq!(call(&mut ctx), Err(BorthError::Bye(0)));
+        ctx.test(&[], "");
+    }
+
+    #[test]
+    fn test2_bye_keeps_the_stack() {
+        let mut ctx = create_context();
+        let _ = ctx.push_value(1);
+        let _ = ctx.push_value(3);
+        assert_eq!(call(&mut ctx), Err(BorthError::Bye(0)));
+        ctx.test(&[1, 3], "");
+    }
+}
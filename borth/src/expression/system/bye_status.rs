@@ -0,0 +1,32 @@
+use crate::{context::*, errors::*};
+
+/// Stop the program, with the top value as exit status.
+pub fn call(ctx: &mut BorthContext) -> BorthResult<()> {
+    let status = ctx.pop_value()?;
+    Err(BorthError::Bye(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_context() -> BorthContext {
+        BorthContext::with_stack_size(10)
+    }
+
+    #[test]
+    fn test1_bye_with_status() {
+        let mut ctx = create_context();
+        let _ = ctx.push_value(1);
+        let _ = ctx.push_value(3);
+        assert_eq!(call(&mut ctx), Err(BorthError::Bye(3)));
+        ctx.test(&[1], "");
+    }
+
+    #[test]
+    fn test2_bye_status_without_status() {
+        let mut ctx = create_context();
+        assert_eq!(call(&mut ctx), Err(BorthError::StackUnderflow));
+        ctx.test(&[], "");
+    }
+}
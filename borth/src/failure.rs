@@ -0,0 +1,52 @@
+use super::json::BorthJson;
+use borth::{BorthError, BorthInterpreter, BorthPosition};
+
+/// Error a source failed with, and where, kept while the next sources run
+pub struct BorthFailure {
+    name: String,
+    source: Option<String>,
+    position: Option<BorthPosition>,
+    word: Option<String>,
+}
+
+impl BorthFailure {
+    /// Create a new BorthFailure instance from the last error of the interpreter,
+    /// unless its last run ended without one or with `bye`
+    pub fn from_last_error(interpreter: &BorthInterpreter) -> Option<Self> {
+        let error = match interpreter.last_error() {
+            None | Some(BorthError::Bye(_)) => return None,
+            Some(error) => error,
+        };
+        Some(Self {
+            name: error.to_string(),
+            source: interpreter.last_error_source().map(str::to_string),
+            position: interpreter.last_error_position(),
+            word: interpreter.last_error_word().map(str::to_string),
+        })
+    }
+
+    /// Describe the error with its source, position and word
+    pub fn to_json(&self) -> BorthJson {
+        let position = self.position;
+        BorthJson::object(vec![
+            ("name", BorthJson::String(self.name.to_string())),
+            (
+                "source",
+                BorthJson::from_option(self.source.as_deref(), string),
+            ),
+            (
+                "line",
+                BorthJson::from_option(position, |p| BorthJson::Number(p.line as i128)),
+            ),
+            (
+                "column",
+                BorthJson::from_option(position, |p| BorthJson::Number(p.column as i128)),
+            ),
+            ("word", BorthJson::from_option(self.word.as_deref(), string)),
+        ])
+    }
+}
+
+fn string(str: &str) -> BorthJson {
+    BorthJson::String(str.to_string())
+}
@@ -0,0 +1,221 @@
+use super::{parser, token::BorthToken};
+
+const INDENT: &str = "  ";
+
+/// Word, `."` string or comment of the source, with the line breaks before it
+struct BorthPiece {
+    text: String,
+    breaks: usize,
+}
+
+/// Lay out Borth code in a uniform style: lowercase words, one definition per line
+/// and indented `if` bodies. Strings and comments are kept as written.
+pub struct BorthFormatter {
+    lines: Vec<String>,
+    line: String,
+    depth: usize,
+    break_pending: bool,
+}
+
+impl BorthFormatter {
+    /// Return the code formatted, ending with a new line if it is not empty
+    pub fn format(code: &str) -> String {
+        let mut formatter = Self {
+            lines: vec![],
+            line: String::new(),
+            depth: 0,
+            break_pending: false,
+        };
+        let pieces = split_pieces(code);
+        let mut rest = pieces.as_slice();
+        while let Some(piece) = rest.first() {
+            if piece.text == ":" {
+                let end = rest.iter().position(|piece| piece.text == ";");
+                let (definition, after) = rest.split_at(end.map_or(rest.len(), |end| end + 1));
+                formatter.put_definition(definition);
+                rest = after;
+            } else {
+                formatter.put_code(piece);
+                rest = &rest[1..];
+            }
+        }
+        formatter.finish()
+    }
+
+    /// Place code outside definitions, keeping its line breaks unless it is in an `if`
+    fn put_code(&mut self, piece: &BorthPiece) {
+        if self.depth == 0 && piece.breaks > 0 {
+            self.start_line();
+            if piece.breaks > 1 && self.lines.last().is_some_and(|line| !line.is_empty()) {
+                self.lines.push(String::new());
+            }
+        }
+        self.put_block_piece(&piece.text);
+    }
+
+    /// Place a definition in one line, or with its body in the next lines if it has an `if`
+    fn put_definition(&mut self, definition: &[BorthPiece]) {
+        self.start_line();
+        if !definition.iter().any(|piece| piece.text == "if") {
+            definition.iter().for_each(|piece| self.put(&piece.text));
+            self.start_line();
+            return;
+        }
+        let header = match definition.get(2) {
+            Some(piece) if piece.text.starts_with('(') => 3,
+            _ => 2,
+        };
+        let (header, body) = definition.split_at(header.min(definition.len()));
+        header.iter().for_each(|piece| self.put(&piece.text));
+        self.start_line();
+        self.depth = 1;
+        body.iter()
+            .for_each(|piece| self.put_block_piece(&piece.text));
+        self.start_line();
+        self.depth = 0;
+        self.break_pending = false;
+    }
+
+    /// Place a piece, starting and indenting lines around `if`, `else` and `then`
+    fn put_block_piece(&mut self, text: &str) {
+        if self.break_pending && text != ";" {
+            self.start_line();
+        }
+        self.break_pending = false;
+        match text {
+            "if" => {
+                self.put(text);
+                self.start_line();
+                self.depth += 1;
+            }
+            "else" => {
+                self.start_line();
+                self.depth = self.depth.saturating_sub(1);
+                self.put(text);
+                self.start_line();
+                self.depth += 1;
+            }
+            "then" => {
+                self.start_line();
+                self.depth = self.depth.saturating_sub(1);
+                self.put(text);
+                self.break_pending = true;
+            }
+            _ => self.put(text),
+        }
+    }
+
+    fn put(&mut self, text: &str) {
+        if self.line.is_empty() {
+            self.line = INDENT.repeat(self.depth);
+        } else {
+            self.line.push(' ');
+        }
+        self.line.push_str(text);
+    }
+
+    fn start_line(&mut self) {
+        if !self.line.is_empty() {
+            self.lines.push(self.line.split_off(0));
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.start_line();
+        self.lines
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+}
+
+/// Split the code into lowercase words, and strings and comments as written
+fn split_pieces(code: &str) -> Vec<BorthPiece> {
+    let tokens = parser::parse_tokens(code);
+    let mut iterator = tokens.iter();
+    let mut pieces = vec![];
+    let mut breaks = 0;
+    while let Some(token) = iterator.next() {
+        if token.word.is_empty() {
+            breaks += usize::from(token.whitespace == "\n");
+            continue;
+        }
+        let text = token.word.to_lowercase();
+        let closing = match text.as_str() {
+            ".\"" => Some('"'),
+            "(" => Some(')'),
+            _ => None,
+        };
+        let (text, last) = match closing {
+            Some(closing) => verbatim(code, token, &mut iterator, closing),
+            None => (text, token),
+        };
+        pieces.push(BorthPiece { text, breaks });
+        breaks = usize::from(last.whitespace == "\n");
+    }
+    pieces
+}
+
+/// Take the tokens up to the one ending with `closing`, or to the end of the code,
+/// and return the code they span and the last one
+fn verbatim<'a>(
+    code: &str,
+    first: &'a BorthToken<'a>,
+    iterator: &mut parser::BorthIterator<'a>,
+    closing: char,
+) -> (String, &'a BorthToken<'a>) {
+    let (end, last) = match iterator.find(|token| token.word.ends_with(closing)) {
+        Some(last) => (last.position.offset + last.word.len(), last),
+        None => (code.len(), first),
+    };
+    let text = code.get(first.position.offset..end).unwrap_or_default();
+    (text.trim_end().to_string(), last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_format(code: &str, expected: &str) {
+        assert_eq!(BorthFormatter::format(code), expected);
+        assert_eq!(BorthFormatter::format(expected), expected);
+    }
+
+    #[test]
+    fn test1_lowercase_words_and_keep_lines() {
+        assert_format("1 2 +   DUP\n\n\n\n  . CR", "1 2 + dup\n\n. cr\n");
+        assert_format("", "");
+    }
+
+    #[test]
+    fn test2_one_definition_per_line() {
+        assert_format(
+            "1 : SQ\n  DUP * ;  : CUBE ( n -- n )\nDUP SQ * ; 3 CUBE .",
+            "1\n: sq dup * ;\n: cube ( n -- n ) dup sq * ;\n3 cube .\n",
+        );
+    }
+
+    #[test]
+    fn test3_indent_if_bodies() {
+        assert_format(
+            ": MAX ( a b -- c ) OVER OVER < IF SWAP THEN DROP ;",
+            ": max ( a b -- c )\n  over over < if\n    swap\n  then\n  drop ;\n",
+        );
+        assert_format(
+            ": F DUP 0 = IF DROP 2 ELSE DUP 1 = IF DROP 3 ELSE DROP 4 THEN THEN ;",
+            concat!(
+                ": f\n  dup 0 = if\n    drop 2\n  else\n    dup 1 = if\n      drop 3\n",
+                "    else\n      drop 4\n    then\n  then ;\n"
+            ),
+        );
+    }
+
+    #[test]
+    fn test4_keep_strings_and_comments_as_written() {
+        assert_format(
+            ": HI .\"  Hello,  World!\" ( Says HI ) CR ;",
+            ": hi .\"  Hello,  World!\" ( Says HI ) cr ;\n",
+        );
+        assert_format("1 ( not\nclosed  ", "1 ( not\nclosed\n");
+    }
+}
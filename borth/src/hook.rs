@@ -0,0 +1,14 @@
+use super::{context::BorthContext, errors::BorthResult, step::BorthStep};
+
+/// Observer of the evaluation, called before each step it takes
+pub trait BorthHook {
+    /// Look at the step about to be taken and the context it runs in.
+    /// An error stops the evaluation as if the step had failed with it.
+    fn before_step(&mut self, ctx: &BorthContext, step: &BorthStep) -> BorthResult<()>;
+
+    /// Look at the context when a run of some code is over and its errors
+    /// were reported. An error is kept as the last error of the run.
+    fn after_run(&mut self, _ctx: &BorthContext) -> BorthResult<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,497 @@
+//! Binary image of the words defined with `:`, so a later run can restore them.
+//!
+//! Integers are little endian. Counts and positions are 64-bit unsigned integers and
+//! strings are their byte length followed by their UTF-8 bytes. An image is:
+//!
+//! - the magic bytes `BRTHIMG\0`, the format version (16 bits, currently 2) and the
+//!   cell width in bytes (8 bits). Images of another cell width are rejected.
+//! - the words: their count, then each word as its name, the stack effect checked
+//!   when it returns and its body. The effect is a byte, 1 if there is one followed
+//!   by its inputs and outputs or 0 if there is none, and is missing in version 1
+//!   images. Words are numbered from 0 in this order, and a word only refers to
+//!   words before it, so a definition used by several words is stored once and
+//!   stays shared when loaded.
+//! - the dictionary entries: their count, then each entry as its name and the number
+//!   of the word it refers to.
+//!
+//! A body is its count of nodes, then each node as the offset, line and column of its
+//! position followed by a tag byte and the tag's data:
+//!
+//! | Tag | Expression            | Data                          |
+//! |-----|-----------------------|-------------------------------|
+//! | 0   | number                | the cell                      |
+//! | 1   | builtin word          | its name                      |
+//! | 2   | word from the host    | its name                      |
+//! | 3   | `."`                  | the string                    |
+//! | 4   | `if`                  | the if body, then else body   |
+//! | 5   | word defined with `:` | its number                    |
+//! | 6   | unknown word          | the word                      |
+//! | 7   | incomplete statement  |                               |
+//! | 8   | invalid word          |                               |
+//! | 9   | forbidden word        |                               |
+//! | 10  | word limit exceeded   |                               |
+//! | 11  | code limit exceeded   |                               |
+//! | 12  | word created          |                               |
+//! | 13  | stack effect mismatch | the word                      |
+//!
+//! Builtin words and `."` are restored through the sandbox of the loading dictionary,
+//! the words count against its limits, and words from the host must be registered
+//! before loading. Images with `if`s nested more than 256 deep are rejected.
+
+pub mod reader;
+pub mod writer;
+
+use super::{
+    callgraph::{self, BorthWordIds, word_id},
+    context::BorthItem,
+    dict::{BorthDict, builtin_name},
+    effect::BorthEffect,
+    errors::*,
+    expression::BorthExpression,
+    node::BorthNode,
+    position::BorthPosition,
+};
+use reader::BorthImageReader;
+use std::rc::Rc;
+use writer::BorthImageWriter;
+
+const MAGIC: &[u8; 8] = b"BRTHIMG\0";
+const VERSION: u16 = 2;
+const CELL_WIDTH: usize = size_of::<BorthItem>();
+/// Deepest nesting of `if`s read, so a crafted image can not overflow the native stack
+const MAX_NESTING: usize = 256;
+
+const NUMBER: u8 = 0;
+const OPERATION: u8 = 1;
+const NATIVE: u8 = 2;
+const DOT_QUOTE: u8 = 3;
+const IF_ELSE_THEN: u8 = 4;
+const WORD: u8 = 5;
+const UNKNOWN_WORD: u8 = 6;
+const INCOMPLETE_STATEMENT: u8 = 7;
+const INVALID_WORD: u8 = 8;
+const FORBIDDEN_WORD: u8 = 9;
+const WORD_LIMIT_EXCEEDED: u8 = 10;
+const CODE_LIMIT_EXCEEDED: u8 = 11;
+const WORD_CREATED: u8 = 12;
+const STACK_EFFECT_MISMATCH: u8 = 13;
+
+/// Return the image of the words defined with `:` in the dictionary
+pub fn save(dict: &BorthDict) -> Vec<u8> {
+    let entries = dict.user_words();
+    let words = callgraph::sorted_words(entries.iter().map(|(_, word)| Rc::clone(word)));
+    let ids = callgraph::word_ids(&words);
+    let mut writer = BorthImageWriter::new();
+    writer.write_bytes(MAGIC);
+    writer.write_u16(VERSION);
+    writer.write_u8(CELL_WIDTH as u8);
+    writer.write_usize(words.len());
+    for word in &words {
+        if let BorthExpression::Word(name, body, code) = word.as_ref() {
+            writer.write_str(name);
+            write_effect(&mut writer, code.effect());
+            write_nodes(&mut writer, body, &ids);
+        }
+    }
+    writer.write_usize(entries.len());
+    for (name, word) in entries {
+        writer.write_str(name);
+        writer.write_usize(word_id(&ids, word));
+    }
+    writer.into_bytes()
+}
+
+/// Add the words of the image to the dictionary, shadowing the ones with the same
+/// names. The dictionary is left unchanged if the image can not be loaded.
+pub fn load(dict: &mut BorthDict, bytes: &[u8]) -> BorthResult<()> {
+    let mut reader = BorthImageReader::new(bytes);
+    let version = read_header(&mut reader)?;
+    let (words, code_size) = read_words(&mut reader, dict, version)?;
+    let mut entries = vec![];
+    for _ in 0..reader.read_usize()? {
+        let name = reader.read_string()?;
+        entries.push((name, read_word(&mut reader, &words)?));
+    }
+    if !reader.is_at_end() {
+        return Err(BorthError::InvalidImage);
+    }
+    dict.reserve(words.len(), code_size)?;
+    for (name, word) in entries {
+        dict.insert(&name, word);
+    }
+    Ok(())
+}
+
+/// Read the words, compiled in the dictionary, and the total size of their bodies
+fn read_words(
+    reader: &mut BorthImageReader,
+    dict: &BorthDict,
+    version: u16,
+) -> BorthResult<(Vec<Rc<BorthExpression>>, usize)> {
+    let mut words = vec![];
+    let mut code_size = 0;
+    for _ in 0..reader.read_usize()? {
+        let name = reader.read_string()?;
+        let effect = match version {
+            1 => None,
+            _ => read_effect(reader)?,
+        };
+        let body = read_nodes(reader, dict, &words, 0)?;
+        code_size += body
+            .iter()
+            .map(|node| node.expression().size())
+            .sum::<usize>();
+        words.push(Rc::new(dict.compile_word(&name, body, effect)));
+    }
+    Ok((words, code_size))
+}
+
+fn write_effect(writer: &mut BorthImageWriter, effect: Option<BorthEffect>) {
+    match effect {
+        Some(effect) => {
+            writer.write_u8(1);
+            writer.write_usize(effect.inputs);
+            writer.write_usize(effect.outputs);
+        }
+        None => writer.write_u8(0),
+    }
+}
+
+fn write_nodes(writer: &mut BorthImageWriter, nodes: &[BorthNode], ids: &BorthWordIds) {
+    writer.write_usize(nodes.len());
+    for node in nodes {
+        let position = node.position();
+        writer.write_usize(position.offset);
+        writer.write_usize(position.line);
+        writer.write_usize(position.column);
+        write_expression(writer, node, ids);
+    }
+}
+
+fn write_expression(writer: &mut BorthImageWriter, node: &BorthNode, ids: &BorthWordIds) {
+    match node.expression() {
+        BorthExpression::Number(value) => {
+            writer.write_u8(NUMBER);
+            writer.write_item(*value);
+        }
+        BorthExpression::Operation(operation) => {
+            writer.write_u8(OPERATION);
+            writer.write_str(builtin_name(*operation).unwrap_or_default());
+        }
+        BorthExpression::Native(native) => {
+            writer.write_u8(NATIVE);
+            writer.write_str(native.name());
+        }
+        BorthExpression::DotQuote(str) => {
+            writer.write_u8(DOT_QUOTE);
+            writer.write_str(str);
+        }
+        BorthExpression::IfElseThen(if_block, else_block) => {
+            writer.write_u8(IF_ELSE_THEN);
+            write_nodes(writer, if_block, ids);
+            write_nodes(writer, else_block, ids);
+        }
+        BorthExpression::Word(..) => {
+            writer.write_u8(WORD);
+            writer.write_usize(word_id(ids, node.expression()));
+        }
+        BorthExpression::UnknownWord(word) => {
+            writer.write_u8(UNKNOWN_WORD);
+            writer.write_str(word);
+        }
+        BorthExpression::StackEffectMismatch(word) => {
+            writer.write_u8(STACK_EFFECT_MISMATCH);
+            writer.write_str(word);
+        }
+        expression => writer.write_u8(unit_tag(expression)),
+    }
+}
+
+fn unit_tag(expression: &BorthExpression) -> u8 {
+    match expression {
+        BorthExpression::IncompleteStatement => INCOMPLETE_STATEMENT,
+        BorthExpression::InvalidWord => INVALID_WORD,
+        BorthExpression::ForbiddenWord => FORBIDDEN_WORD,
+        BorthExpression::WordLimitExceeded => WORD_LIMIT_EXCEEDED,
+        BorthExpression::CodeLimitExceeded => CODE_LIMIT_EXCEEDED,
+        _ => WORD_CREATED,
+    }
+}
+
+/// Check the header and return the format version
+fn read_header(reader: &mut BorthImageReader) -> BorthResult<u16> {
+    let magic = reader.read_bytes(MAGIC.len())?;
+    let version = reader.read_u16()?;
+    if magic != MAGIC || !(1..=VERSION).contains(&version) {
+        return Err(BorthError::InvalidImage);
+    }
+    if usize::from(reader.read_u8()?) != CELL_WIDTH {
+        return Err(BorthError::ImageCellWidthMismatch);
+    }
+    Ok(version)
+}
+
+fn read_effect(reader: &mut BorthImageReader) -> BorthResult<Option<BorthEffect>> {
+    match reader.read_u8()? {
+        0 => Ok(None),
+        1 => Ok(Some(BorthEffect::new(
+            reader.read_usize()?,
+            reader.read_usize()?,
+        ))),
+        _ => Err(BorthError::InvalidImage),
+    }
+}
+
+/// Read a body nested in `depth` `if`s
+fn read_nodes(
+    reader: &mut BorthImageReader,
+    dict: &BorthDict,
+    words: &[Rc<BorthExpression>],
+    depth: usize,
+) -> BorthResult<Vec<BorthNode>> {
+    if depth > MAX_NESTING {
+        return Err(BorthError::InvalidImage);
+    }
+    let mut nodes = vec![];
+    for _ in 0..reader.read_usize()? {
+        let offset = reader.read_usize()?;
+        let line = reader.read_usize()?;
+        let column = reader.read_usize()?;
+        let position = BorthPosition::new(offset, line, column);
+        let expression = read_expression(reader, dict, words, depth)?;
+        nodes.push(BorthNode::new(position, expression));
+    }
+    Ok(nodes)
+}
+
+fn read_expression(
+    reader: &mut BorthImageReader,
+    dict: &BorthDict,
+    words: &[Rc<BorthExpression>],
+    depth: usize,
+) -> BorthResult<Rc<BorthExpression>> {
+    let expression = match reader.read_u8()? {
+        NUMBER => BorthExpression::Number(reader.read_item()?),
+        OPERATION => dict
+            .builtin(&reader.read_string()?)
+            .ok_or(BorthError::InvalidImage)?,
+        NATIVE => return read_native(reader, dict),
+        DOT_QUOTE => dict.dot_quote(reader.read_string()?),
+        IF_ELSE_THEN => BorthExpression::IfElseThen(
+            read_nodes(reader, dict, words, depth + 1)?,
+            read_nodes(reader, dict, words, depth + 1)?,
+        ),
+        WORD => return read_word(reader, words),
+        UNKNOWN_WORD => BorthExpression::UnknownWord(reader.read_string()?),
+        STACK_EFFECT_MISMATCH => BorthExpression::StackEffectMismatch(reader.read_string()?),
+        INCOMPLETE_STATEMENT => BorthExpression::IncompleteStatement,
+        INVALID_WORD => BorthExpression::InvalidWord,
+        FORBIDDEN_WORD => BorthExpression::ForbiddenWord,
+        WORD_LIMIT_EXCEEDED => BorthExpression::WordLimitExceeded,
+        CODE_LIMIT_EXCEEDED => BorthExpression::CodeLimitExceeded,
+        WORD_CREATED => BorthExpression::WordCreated,
+        _ => return Err(BorthError::InvalidImage),
+    };
+    Ok(Rc::new(expression))
+}
+
+/// Return the word with the number read, which must be already loaded
+fn read_word(
+    reader: &mut BorthImageReader,
+    words: &[Rc<BorthExpression>],
+) -> BorthResult<Rc<BorthExpression>> {
+    let word = words
+        .get(reader.read_usize()?)
+        .ok_or(BorthError::InvalidImage)?;
+    Ok(Rc::clone(word))
+}
+
+/// Return the word from the host with the name read, which must be registered
+fn read_native(
+    reader: &mut BorthImageReader,
+    dict: &BorthDict,
+) -> BorthResult<Rc<BorthExpression>> {
+    let name = reader.read_string()?;
+    match dict.try_detect(&name) {
+        Some(native) if matches!(native.as_ref(), BorthExpression::Native(_)) => Ok(native),
+        _ => Err(BorthError::UnknownWord(name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{limits::BorthLimits, native::BorthNative, parser, sandbox::BorthSandbox};
+
+    fn create_dict(code: &str) -> BorthDict {
+        let mut dict = BorthDict::new();
+        let tokens = parser::parse_tokens(code);
+        parser::parse_expressions(tokens, &mut dict);
+        dict
+    }
+
+    fn callee(dict: &BorthDict, word: &str, index: usize) -> Option<Rc<BorthExpression>> {
+        match dict.try_detect(word)?.as_ref() {
+            BorthExpression::Word(_, body, _) => Some(body.get(index)?.shared_expression()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test1_restore_the_words_without_their_later_redefinitions() {
+        let image = save(&create_dict(": foo 1 ; : bar foo 2 ; : foo 3 ;"));
+        let mut dict = BorthDict::new();
+        assert_eq!(load(&mut dict, &image), Ok(()));
+        assert_eq!(
+            dict.try_detect("bar"),
+            create_dict(": foo 1 ; : bar foo 2 ;").try_detect("bar")
+        );
+        assert_eq!(
+            dict.try_detect("foo"),
+            create_dict(": foo 3 ;").try_detect("foo")
+        );
+    }
+
+    #[test]
+    fn test2_keep_a_word_shared_by_several_words() {
+        let image = save(&create_dict(": foo 1 ; : bar foo ; : baz foo ; : foo 2 ;"));
+        let mut dict = BorthDict::new();
+        assert_eq!(load(&mut dict, &image), Ok(()));
+        let from_bar = callee(&dict, "bar", 0);
+        let from_baz = callee(&dict, "baz", 0);
+        assert!(
+            from_bar
+                .zip(from_baz)
+                .is_some_and(|(a, b)| Rc::ptr_eq(&a, &b))
+        );
+    }
+
+    #[test]
+    fn test3_store_a_shared_word_once() {
+        let once = save(&create_dict(": foo 1 2 3 4 5 ; : bar foo ;"));
+        let twice = save(&create_dict(": foo 1 2 3 4 5 ; : bar foo foo ;"));
+        assert_eq!(twice.len() - once.len(), 3 * 8 + 1 + 8);
+    }
+
+    #[test]
+    fn test4_reject_another_cell_width() {
+        let mut image = save(&create_dict(": foo 1 ;"));
+        image[MAGIC.len() + 2] = 8;
+        let mut dict = BorthDict::new();
+        assert_eq!(
+            load(&mut dict, &image),
+            Err(BorthError::ImageCellWidthMismatch)
+        );
+    }
+
+    #[test]
+    fn test5_reject_bytes_that_are_not_an_image() {
+        let mut dict = BorthDict::new();
+        assert_eq!(load(&mut dict, b"1 2 3"), Err(BorthError::InvalidImage));
+        let mut image = save(&create_dict(": foo 1 ;"));
+        image.push(0);
+        assert_eq!(load(&mut dict, &image), Err(BorthError::InvalidImage));
+        assert_eq!(dict.try_detect("foo"), None);
+    }
+
+    #[test]
+    fn test6_keep_the_effect_checked_at_runtime() {
+        let mut dict = BorthDict::new();
+        dict.enable_strict();
+        let native = BorthNative::new("host", |_| Ok(()));
+        assert_eq!(dict.register_native("host", native), Ok(()));
+        let tokens = parser::parse_tokens(": foo ( -- n ) host ;");
+        parser::parse_expressions(tokens, &mut dict);
+        let image = save(&dict);
+        assert_eq!(load(&mut dict, &image), Ok(()));
+        let effect = match dict.try_detect("foo").as_deref() {
+            Some(BorthExpression::Word(_, _, code)) => code.effect(),
+            _ => None,
+        };
+        assert_eq!(effect, Some(BorthEffect::new(0, 1)));
+    }
+
+    #[test]
+    fn test7_restore_builtins_through_the_sandbox() {
+        let image = save(&create_dict(": foo 1 . ;"));
+        let sandbox = BorthSandbox {
+            output: true,
+            ..Default::default()
+        };
+        let mut dict = BorthDict::with_sandbox(sandbox);
+        assert_eq!(load(&mut dict, &image), Ok(()));
+        let forbidden = callee(&dict, "foo", 1);
+        assert_eq!(forbidden.as_deref(), Some(&BorthExpression::ForbiddenWord));
+    }
+
+    #[test]
+    fn test8_restore_dot_quote_through_the_sandbox() {
+        let image = save(&create_dict(": foo .\" leaked\" ;"));
+        let sandbox = BorthSandbox {
+            output: true,
+            ..Default::default()
+        };
+        let mut dict = BorthDict::with_sandbox(sandbox);
+        assert_eq!(load(&mut dict, &image), Ok(()));
+        let forbidden = callee(&dict, "foo", 0);
+        assert_eq!(forbidden.as_deref(), Some(&BorthExpression::ForbiddenWord));
+    }
+
+    #[test]
+    fn test9_count_the_words_against_the_limits() {
+        let image = save(&create_dict(": a 1 ; : b 2 ; : c 3 ; : d a b c ;"));
+        let mut dict = BorthDict::new();
+        let limits = BorthLimits {
+            max_words: Some(1),
+            ..Default::default()
+        };
+        dict.set_limits(limits);
+        assert_eq!(load(&mut dict, &image), Err(BorthError::WordLimitExceeded));
+        assert_eq!(dict.try_detect("a"), None);
+
+        let limits = BorthLimits {
+            max_code: Some(5),
+            ..Default::default()
+        };
+        dict.set_limits(limits);
+        assert_eq!(load(&mut dict, &image), Err(BorthError::CodeLimitExceeded));
+        assert_eq!(dict.try_detect("d"), None);
+
+        dict.set_limits(BorthLimits::default());
+        assert_eq!(load(&mut dict, &image), Ok(()));
+        dict.set_limits(BorthLimits {
+            max_words: Some(4),
+            ..Default::default()
+        });
+        let tokens = parser::parse_tokens(": e 5 ;");
+        let nodes = parser::parse_expressions(tokens, &mut dict);
+        let expression = nodes.first().map(|node| node.shared_expression());
+        assert_eq!(
+            expression.as_deref(),
+            Some(&BorthExpression::WordLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn test10_reject_ifs_nested_too_deep() {
+        let mut writer = BorthImageWriter::new();
+        writer.write_bytes(MAGIC);
+        writer.write_u16(VERSION);
+        writer.write_u8(CELL_WIDTH as u8);
+        writer.write_usize(1);
+        writer.write_str("foo");
+        writer.write_u8(0);
+        for _ in 0..1_000_000 {
+            writer.write_usize(1);
+            writer.write_usize(0);
+            writer.write_usize(0);
+            writer.write_usize(0);
+            writer.write_u8(IF_ELSE_THEN);
+        }
+        let mut dict = BorthDict::new();
+        assert_eq!(
+            load(&mut dict, &writer.into_bytes()),
+            Err(BorthError::InvalidImage)
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use crate::{context::BorthItem, errors::*};
+
+/// Read the bytes of an image in order, failing with `InvalidImage` when they end early
+pub struct BorthImageReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> BorthImageReader<'a> {
+    /// Create a new BorthImageReader instance at the start of the bytes
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+
+    /// Read the next `len` bytes
+    pub fn read_bytes(&mut self, len: usize) -> BorthResult<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .ok_or(BorthError::InvalidImage)?;
+        let bytes = self
+            .bytes
+            .get(self.offset..end)
+            .ok_or(BorthError::InvalidImage)?;
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    /// Read a single byte
+    pub fn read_u8(&mut self) -> BorthResult<u8> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    /// Read a 16-bit unsigned integer
+    pub fn read_u16(&mut self) -> BorthResult<u16> {
+        Ok(u16::from_le_bytes(self.read_array()?))
+    }
+
+    /// Read a count or a position written as a 64-bit unsigned integer
+    pub fn read_usize(&mut self) -> BorthResult<usize> {
+        let value = u64::from_le_bytes(self.read_array()?);
+        usize::try_from(value).or(Err(BorthError::InvalidImage))
+    }
+
+    /// Read a cell
+    pub fn read_item(&mut self) -> BorthResult<BorthItem> {
+        Ok(BorthItem::from_le_bytes(self.read_array()?))
+    }
+
+    /// Read a string written after its length
+    pub fn read_string(&mut self) -> BorthResult<String> {
+        let len = self.read_usize()?;
+        let bytes = self.read_bytes(len)?;
+        String::from_utf8(bytes.to_vec()).or(Err(BorthError::InvalidImage))
+    }
+
+    /// Whether all the bytes were read
+    pub fn is_at_end(&self) -> bool {
+        self.offset == self.bytes.len()
+    }
+
+    fn read_array<const N: usize>(&mut self) -> BorthResult<[u8; N]> {
+        self.read_bytes(N)?
+            .try_into()
+            .or(Err(BorthError::InvalidImage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_read_integers_in_little_endian() {
+        let mut reader = BorthImageReader::new(&[2, 1, 0xfe, 0xff]);
+        assert_eq!(reader.read_u16(), Ok(0x0102));
+        assert_eq!(reader.read_item(), Ok(-2));
+        assert!(reader.is_at_end());
+    }
+
+    #[test]
+    fn test2_read_string_after_its_length() {
+        let mut reader = BorthImageReader::new(&[2, 0, 0, 0, 0, 0, 0, 0, b'a', b'b']);
+        assert_eq!(reader.read_string(), Ok("ab".to_string()));
+    }
+
+    #[test]
+    fn test3_fail_when_the_bytes_end_early() {
+        let mut reader = BorthImageReader::new(&[5, 0, 0, 0, 0, 0, 0, 0, b'a']);
+        assert_eq!(reader.read_string(), Err(BorthError::InvalidImage));
+    }
+}
@@ -0,0 +1,69 @@
+use crate::context::BorthItem;
+
+/// Build the bytes of an image, writing integers in little endian
+pub struct BorthImageWriter {
+    bytes: Vec<u8>,
+}
+
+impl BorthImageWriter {
+    /// Create a new BorthImageWriter instance with no bytes
+    pub fn new() -> Self {
+        Self { bytes: vec![] }
+    }
+
+    /// Write the bytes as they are
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    /// Write a single byte
+    pub fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    /// Write a 16-bit unsigned integer
+    pub fn write_u16(&mut self, value: u16) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// Write a count or a position as a 64-bit unsigned integer
+    pub fn write_usize(&mut self, value: usize) {
+        self.write_bytes(&(value as u64).to_le_bytes());
+    }
+
+    /// Write a cell
+    pub fn write_item(&mut self, value: BorthItem) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// Write the length of the string followed by its UTF-8 bytes
+    pub fn write_str(&mut self, str: &str) {
+        self.write_usize(str.len());
+        self.write_bytes(str.as_bytes());
+    }
+
+    /// Return the written bytes
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_write_integers_in_little_endian() {
+        let mut writer = BorthImageWriter::new();
+        writer.write_u16(0x0102);
+        writer.write_item(-2);
+        assert_eq!(writer.into_bytes(), [2, 1, 0xfe, 0xff]);
+    }
+
+    #[test]
+    fn test2_write_string_after_its_length() {
+        let mut writer = BorthImageWriter::new();
+        writer.write_str("ab");
+        assert_eq!(writer.into_bytes(), [2, 0, 0, 0, 0, 0, 0, 0, b'a', b'b']);
+    }
+}
@@ -1,41 +1,520 @@
-use super::{context::*, dict::*, errors::*, parser};
+use super::{
+    backtrace::BorthBacktrace, callgraph, checker, checker::BorthChecker, context::*,
+    definition::BorthDefinition, diagnostic::BorthDiagnostic, dict::*, effect::BorthEffect,
+    errors::*, expression::BorthExpression, hook::BorthHook, image, issue, issue::BorthIssue,
+    limits::BorthLimits, native::BorthNative, node::BorthNode, parser, parser::BorthTokens,
+    position::BorthPosition, recovery::BorthRecovery, sandbox::BorthSandbox, sink::BorthSink,
+    stats::BorthStats, token::BorthToken, transpiler,
+};
+use std::rc::Rc;
 
-/// Store interpreter's context and dictionary
+/// Name of the source in diagnostics of code that was not named
+const UNNAMED_SOURCE: &str = "<code>";
+
+/// Store interpreter's context and dictionary.
+/// Both live across evaluations until the interpreter is reset.
 pub struct BorthInterpreter {
     ctx: BorthContext,
     dict: BorthDict,
+    diagnostics: bool,
+    sources: Vec<String>,
+    source: usize,
+    backtrace: bool,
+    silent: bool,
+    recovery: Option<BorthRecovery>,
+    last_error: Option<BorthError>,
+    error_position: Option<BorthPosition>,
+    error_word: Option<String>,
+    limits: BorthLimits,
+    sandbox: BorthSandbox,
+    optimize: bool,
+    strict: bool,
 }
 
 impl BorthInterpreter {
-    /// Create a new BorthInterpreter instance with the given stack size
+    /// Stack size in bytes used by `new`
+    pub const DEFAULT_STACK_SIZE: usize = 128_000;
+
+    /// Create a new BorthInterpreter instance with the default stack size
+    pub fn new() -> Self {
+        Self::with_stack_size(Self::DEFAULT_STACK_SIZE)
+    }
+
+    /// Create a new BorthInterpreter instance with the given stack size in bytes
     pub fn with_stack_size(stack_size: usize) -> Self {
+        Self::with_sandbox(stack_size, BorthSandbox::default())
+    }
+
+    /// Create a new BorthInterpreter instance with the given stack size in bytes,
+    /// where the word groups denied by the sandbox fail with `ForbiddenWord`
+    pub fn with_sandbox(stack_size: usize, sandbox: BorthSandbox) -> Self {
         Self {
             ctx: BorthContext::with_stack_size(stack_size),
-            dict: BorthDict::new(),
+            dict: BorthDict::with_sandbox(sandbox),
+            diagnostics: false,
+            sources: vec![],
+            source: 0,
+            backtrace: false,
+            silent: false,
+            recovery: None,
+            last_error: None,
+            error_position: None,
+            error_word: None,
+            limits: BorthLimits::default(),
+            sandbox,
+            optimize: false,
+            strict: false,
         }
     }
 
-    /// Eval the given code and handle errors. Then return the resulting stack and output.
-    pub fn run_code(&mut self, code: &str) -> (&[BorthItem], &str) {
-        if let Err(err) = self.eval(code) {
-            self.ctx.print(&format!("{}\n", err));
+    /// Report errors with the source name, line, column and failing word
+    pub fn enable_diagnostics(&mut self) {
+        self.diagnostics = true;
+    }
+
+    /// Name the code run from now on, so errors in it are reported with that name
+    /// even when they happen while another source runs
+    pub fn set_source(&mut self, name: &str) {
+        let index = match self.sources.iter().position(|source| source == name) {
+            Some(index) => index,
+            None => {
+                self.sources.push(name.to_string());
+                self.sources.len() - 1
+            }
+        };
+        self.source = index + 1;
+    }
+
+    /// Return the name of the source of the position, if it was named
+    pub fn source_name(&self, position: BorthPosition) -> Option<&str> {
+        let index = position.source.checked_sub(1)?;
+        self.sources.get(index).map(String::as_str)
+    }
+
+    /// Print the called words and the data stack after an error
+    pub fn enable_backtrace(&mut self) {
+        self.backtrace = true;
+    }
+
+    /// Keep error messages, backtraces and error counts out of the output,
+    /// for hosts that report the last error themselves
+    pub fn silence_errors(&mut self) {
+        self.silent = true;
+    }
+
+    /// Report errors and resume at the next line instead of stopping,
+    /// then print how many errors occurred
+    pub fn enable_keep_going(&mut self, recovery: BorthRecovery) {
+        self.recovery = Some(recovery);
+    }
+
+    /// Optimize the words defined from now on: fold constants, remove pairs like
+    /// `swap swap` and inline tiny words. Optimized words take fewer steps and
+    /// inlined ones don't show up in backtraces.
+    pub fn enable_optimizer(&mut self) {
+        self.optimize = true;
+        self.dict.enable_optimizer();
+    }
+
+    /// Check the `( a b -- c )` comments right after the names of the words defined
+    /// from now on. A definition whose inferred effect differs from the declared one
+    /// fails with `StackEffectMismatch` instead of adding the word. If the effect can
+    /// not be inferred, the word fails with it when it returns with another depth.
+    pub fn enable_strict(&mut self) {
+        self.strict = true;
+        self.dict.enable_strict();
+    }
+
+    /// Limit the number of expressions evaluated from now on, across runs, or remove
+    /// the limit with `None`. Once exhausted, evaluation fails with `BudgetExhausted`.
+    pub fn set_max_steps(&mut self, max_steps: Option<usize>) {
+        self.ctx.set_max_steps(max_steps);
+    }
+
+    /// Return how many expressions can still be evaluated, if there is a limit
+    pub fn steps_left(&self) -> Option<usize> {
+        self.ctx.steps_left()
+    }
+
+    /// Cap the output size, the number of definitions and their total size.
+    /// Going over a cap fails with its own error.
+    pub fn set_limits(&mut self, limits: BorthLimits) {
+        self.limits = limits;
+        self.ctx.set_max_output(limits.max_output);
+        self.dict.set_limits(limits);
+    }
+
+    /// Write the output through the given sink instead of keeping it in memory
+    pub fn set_output(&mut self, output: Box<dyn BorthSink>) {
+        self.ctx.set_output(output);
+    }
+
+    /// Call the hook before each step of the evaluation, or stop calling it with `None`
+    pub fn set_hook(&mut self, hook: Option<Box<dyn BorthHook>>) {
+        self.ctx.set_hook(hook);
+    }
+
+    /// Add a word implemented by a closure, which may capture state from the host.
+    /// Like words defined with `:`, it shadows previous definitions of the same name
+    /// without changing the words that already use them.
+    pub fn register_word(
+        &mut self,
+        name: &str,
+        word: impl FnMut(&mut BorthContext) -> BorthResult<()> + 'static,
+    ) -> BorthResult<()> {
+        self.dict
+            .register_native(name, BorthNative::new(name, word))
+    }
+
+    /// Translate the code into a standalone Rust program that runs it like
+    /// `run_code` and saves the final stack to `stack_file`. The definitions in the
+    /// code are added to the dictionary, but the code is not evaluated.
+    pub fn transpile(&mut self, code: &str, stack_file: Option<&str>) -> BorthResult<String> {
+        let tokens = parser::parse_tokens(code);
+        let expressions = parser::parse_expressions(tokens, &mut self.dict);
+        transpiler::transpile(&expressions, self.ctx.capacity(), stack_file)
+    }
+
+    /// Check the code without running it: infer the stack effect of the words it
+    /// defines or calls and find where it would underflow, starting with the current
+    /// stack. The definitions in the code are added to the dictionary.
+    pub fn check(&mut self, code: &str) -> Vec<BorthIssue> {
+        let known = self.user_word_addresses();
+        let tokens = parser::parse_tokens(code);
+        let expressions = parser::parse_expressions(tokens, &mut self.dict);
+        let mut roots = self.new_words(&known);
+        callgraph::push_callees(&expressions, &mut roots);
+        let mut checker = BorthChecker::new();
+        let mut issues = checker.infer_words(&callgraph::sorted_words(roots.into_iter()));
+        issues.extend(checker.check_code(&expressions, self.ctx.stack_items().len()));
+        issues
+    }
+
+    /// Return the stack effect inferred for the word, or `None` if it is not defined
+    /// or its effect is not known
+    pub fn stack_effect(&self, word: &str) -> Option<BorthEffect> {
+        let word = self.dict.try_detect(word)?;
+        checker::infer_body(&[BorthNode::new(BorthPosition::default(), word)])
+    }
+
+    /// Find what the parser rejects in the code without running it: unknown words,
+    /// conditionals and definitions that are not closed, and invalid definitions.
+    /// The definitions in the code are added to the dictionary.
+    pub fn diagnose(&mut self, code: &str) -> Vec<BorthIssue> {
+        let known = self.user_word_addresses();
+        let tokens = parser::parse_tokens(code);
+        let expressions = parser::parse_expressions(tokens, &mut self.dict);
+        let mut issues = vec![];
+        for word in self.new_words(&known) {
+            if let BorthExpression::Word(name, body, _) = word.as_ref() {
+                issue::push_parse_errors(body, Some(name), &mut issues);
+            }
         }
-        (self.ctx.stack_items(), self.ctx.output())
+        issue::push_parse_errors(&expressions, None, &mut issues);
+        issues.sort_by_key(|issue| issue.position.offset);
+        issues
+    }
+
+    /// Return where the word was defined with `:`, if it was in the code given to
+    /// this interpreter and it was not shadowed since
+    pub fn definition(&self, word: &str) -> Option<BorthDefinition> {
+        self.dict.definition(word)
     }
 
-    fn eval(&mut self, code: &str) -> BorthResult<()> {
+    /// Return the words defined with `:` that are not in the given addresses
+    fn new_words(&self, known: &[*const BorthExpression]) -> Vec<Rc<BorthExpression>> {
+        let words = self.dict.user_words().into_iter();
+        words
+            .filter(|(_, word)| !known.contains(&Rc::as_ptr(word)))
+            .map(|(_, word)| Rc::clone(word))
+            .collect()
+    }
+
+    fn user_word_addresses(&self) -> Vec<*const BorthExpression> {
+        let words = self.dict.user_words().into_iter();
+        words.map(|(_, word)| Rc::as_ptr(word)).collect()
+    }
+
+    /// Eval the given code, stopping at the first error and returning it.
+    /// Unlike `run_code`, the error is not written to the output.
+    pub fn eval(&mut self, code: &str) -> BorthResult<()> {
         let tokens = parser::parse_tokens(code);
         let expressions = parser::parse_expressions(tokens, &mut self.dict);
+        self.ctx.clear_error_location();
         for exp in expressions {
             exp.eval(&mut self.ctx)?;
         }
         Ok(())
     }
+
+    /// Eval the given code and handle errors. Then return the resulting stack and
+    /// the output kept in memory.
+    pub fn run_code(&mut self, code: &str) -> (&[BorthItem], &str) {
+        self.run(code, true)
+    }
+
+    /// Eval a line of an interactive session like `run_code`, but without the
+    /// count of errors printed at the end in keep-going mode
+    pub fn run_line(&mut self, line: &str) -> (&[BorthItem], &str) {
+        self.run(line, false)
+    }
+
+    fn run(&mut self, code: &str, summary: bool) -> (&[BorthItem], &str) {
+        let tokens = self.tokenize(code);
+        let errors = self.eval_tokens(&tokens);
+        if summary
+            && self.recovery.is_some()
+            && let Err(err) = self.print_summary(errors)
+        {
+            self.last_error = Some(err);
+        }
+        if let Err(err) = self.ctx.finish_hook() {
+            self.last_error = Some(err);
+        }
+        (self.ctx.stack_items(), self.ctx.output())
+    }
+
+    /// Return the last error of the previous run, if any.
+    /// A run stopped by `bye` ends with `Bye` and its status.
+    pub fn last_error(&self) -> Option<&BorthError> {
+        self.last_error.as_ref()
+    }
+
+    /// Return the position of the last error of the previous run, if known
+    pub fn last_error_position(&self) -> Option<BorthPosition> {
+        self.error_position
+    }
+
+    /// Return the name of the source of the last error of the previous run, if known
+    pub fn last_error_source(&self) -> Option<&str> {
+        self.error_position
+            .and_then(|position| self.source_name(position))
+    }
+
+    /// Return the innermost word being called when the last error of the
+    /// previous run happened, if any
+    pub fn last_error_word(&self) -> Option<&str> {
+        self.error_word.as_deref()
+    }
+
+    /// Return the counters of the evaluation since the interpreter was created or reset
+    pub fn stats(&self) -> BorthStats {
+        self.ctx.stats()
+    }
+
+    /// Return the items from the stack, the oldest first
+    pub fn stack_items(&self) -> &[BorthItem] {
+        self.ctx.stack_items()
+    }
+
+    /// Push a value to the stack or return an error if the stack is full
+    pub fn push(&mut self, value: BorthItem) -> BorthResult<()> {
+        self.ctx.push_value(value)
+    }
+
+    /// Pop the top value from the stack or return an error if the stack is empty
+    pub fn pop(&mut self) -> BorthResult<BorthItem> {
+        self.ctx.pop_value()
+    }
+
+    /// Return the names of the defined words, sorted
+    pub fn words(&self) -> Vec<&str> {
+        self.dict.words()
+    }
+
+    /// Return an image of the words defined with `:`, in the format described
+    /// in the `image` module
+    pub fn save_image(&self) -> Vec<u8> {
+        image::save(&self.dict)
+    }
+
+    /// Restore the words of an image, shadowing the ones with the same names.
+    /// Words from the host used by the image must be registered first.
+    pub fn load_image(&mut self, bytes: &[u8]) -> BorthResult<()> {
+        image::load(&mut self.dict, bytes)
+    }
+
+    /// Return the output kept in memory, empty if it is written through a sink
+    pub fn output(&self) -> &str {
+        self.ctx.output()
+    }
+
+    /// Take the output kept in memory, leaving it empty.
+    /// The next output is not separated by a space from the taken one.
+    pub fn take_output(&mut self) -> String {
+        self.ctx.take_output()
+    }
+
+    /// Write a string straight to the output, without the automatic spacing
+    /// and not counted against the output limit
+    pub fn write_output(&mut self, str: &str) -> BorthResult<()> {
+        self.ctx.write(str)
+    }
+
+    /// Flush any output not yet delivered by the sink
+    pub fn flush_output(&mut self) -> BorthResult<()> {
+        self.ctx.flush_output()
+    }
+
+    /// Go back to the initial state: empty stack, builtin words only and no output
+    /// kept in memory. Options and the output sink are kept.
+    pub fn reset(&mut self) {
+        self.ctx.reset();
+        self.dict = BorthDict::with_sandbox(self.sandbox);
+        self.dict.set_limits(self.limits);
+        if self.optimize {
+            self.dict.enable_optimizer();
+        }
+        if self.strict {
+            self.dict.enable_strict();
+        }
+        self.last_error = None;
+        self.error_position = None;
+        self.error_word = None;
+    }
+
+    /// Split the code into tokens, with positions in the current source
+    fn tokenize<'a>(&self, code: &'a str) -> BorthTokens<'a> {
+        let mut tokens = parser::parse_tokens(code);
+        for token in &mut tokens {
+            token.position.source = self.source;
+        }
+        tokens
+    }
+
+    /// Parse and eval the expressions one at a time, so that in keep-going mode the
+    /// rest of a line after an error is dropped before it is parsed, definitions
+    /// included. `bye` stops the evaluation without being reported. Return the number
+    /// of errors.
+    fn eval_tokens(&mut self, tokens: &[BorthToken]) -> usize {
+        let mut errors = 0;
+        let mut iterator = tokens.iter();
+        self.last_error = None;
+        self.error_position = None;
+        self.error_word = None;
+        while let Some(exp) = self.dict.detect_next(&mut iterator) {
+            self.ctx.clear_error_location();
+            match exp.eval(&mut self.ctx) {
+                Ok(()) => {}
+                Err(err @ BorthError::Bye(_)) => {
+                    self.last_error = Some(err);
+                    break;
+                }
+                Err(err) => {
+                    errors += 1;
+                    if !self.recover(err) {
+                        break;
+                    }
+                    parser::skip_line(&mut iterator, exp.position().line);
+                }
+            }
+        }
+        errors
+    }
+
+    /// Report the error and prepare to resume. Return whether the execution can go on.
+    fn recover(&mut self, err: BorthError) -> bool {
+        let (position, word) = self.ctx.error_location();
+        self.error_position = position;
+        self.error_word = word.map(str::to_string);
+        let err = match self.report_error(&err) {
+            Ok(()) => err,
+            Err(output_err) => output_err,
+        };
+        let recovery = self
+            .recovery
+            .filter(|_| err != BorthError::CanNotWriteToOutput);
+        self.last_error = Some(err);
+        match recovery {
+            None => false,
+            Some(BorthRecovery::ResetStack) => {
+                self.ctx.clear_stack();
+                true
+            }
+            Some(BorthRecovery::KeepStack) => true,
+        }
+    }
+
+    fn report_error(&mut self, err: &BorthError) -> BorthResult<()> {
+        if self.silent {
+            return Ok(());
+        }
+        let message = self.describe_error(err);
+        self.ctx.report(&format!("{}\n", message))?;
+        if self.backtrace {
+            let location = self.error_location();
+            let backtrace = BorthBacktrace::new(self.ctx.frames(), self.ctx.stack_items())
+                .at(location.as_deref());
+            let message = backtrace.to_string();
+            self.ctx.report(&message)?;
+        }
+        Ok(())
+    }
+
+    fn print_summary(&mut self, errors: usize) -> BorthResult<()> {
+        if self.silent {
+            return Ok(());
+        }
+        let plural = if errors == 1 { "" } else { "s" };
+        self.ctx.start_line()?;
+        self.ctx.report(&format!("{} error{}\n", errors, plural))
+    }
+
+    fn describe_error(&self, err: &BorthError) -> String {
+        if !self.diagnostics {
+            return err.to_string();
+        }
+        let (position, word) = self.ctx.error_location();
+        let source = position.and_then(|position| self.source_name(position));
+        let source = source.unwrap_or(UNNAMED_SOURCE);
+        BorthDiagnostic::new(source, err, position, word).to_string()
+    }
+
+    /// Return the source name and position of the current error, if both are known
+    fn error_location(&self) -> Option<String> {
+        let (position, _) = self.ctx.error_location();
+        let position = position?;
+        let source = self.source_name(position)?;
+        Some(format!("{}:{}", source, position))
+    }
+}
+
+impl Default for BorthInterpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{sink::writer::BorthWriter, step::BorthStep};
+    use std::{
+        cell::{Cell, RefCell},
+        io::Cursor,
+        rc::Rc,
+    };
+
+    struct StepRecorder(Rc<RefCell<Vec<String>>>);
+
+    impl BorthHook for StepRecorder {
+        fn before_step(&mut self, ctx: &BorthContext, step: &BorthStep) -> BorthResult<()> {
+            let depth = ctx.frames().len();
+            let line = format!("{} {} {:?}", step.describe(), depth, ctx.stack_items());
+            self.0.borrow_mut().push(line);
+            match step.describe().as_str() {
+                "bye" => Err(BorthError::Bye(3)),
+                _ => Ok(()),
+            }
+        }
+
+        fn after_run(&mut self, ctx: &BorthContext) -> BorthResult<()> {
+            self.0
+                .borrow_mut()
+                .push(format!("end {:?}", ctx.stack_items()));
+            Ok(())
+        }
+    }
 
     fn create_interpreter() -> BorthInterpreter {
         BorthInterpreter::with_stack_size(20)
@@ -71,6 +550,15 @@ mod tests {
         assert_eq!(output, "?\n");
     }
 
+    #[test]
+    fn test04_diagnostics_with_position_and_word() {
+        let mut interpreter = create_interpreter();
+        interpreter.enable_diagnostics();
+        interpreter.set_source("main.fth");
+        let (_, output) = interpreter.run_code(": foo\n  1 + ;\n\nfoo");
+        assert_eq!(output, "main.fth:2:5: stack-underflow in word foo\n");
+    }
+
     #[test]
     fn test05_ignore_whitespaces() {
         run_code_and_assert_stack_equals("1 2\n\n 3\n \n4\n 5            6", &[1, 2, 3, 4, 5, 6]);
@@ -216,6 +704,386 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test28_backtrace_innermost_first() {
+        let mut interpreter = create_interpreter();
+        interpreter.enable_backtrace();
+        let (_, output) = interpreter.run_code(": inner + ; : outer 5 inner ; 1 drop outer");
+        assert_eq!(
+            output,
+            "stack-underflow\nBacktrace:\n  inner\n  outer\nStack: <0>\n"
+        );
+    }
+
+    #[test]
+    fn test29_frames_cleared_between_runs() {
+        let mut interpreter = create_interpreter();
+        interpreter.enable_diagnostics();
+        interpreter.set_source("main.fth");
+        interpreter.run_code(": foo + ; foo");
+        let (_, output) = interpreter.run_code("drop");
+        assert_eq!(
+            output,
+            "main.fth:1:7: stack-underflow in word foo\nmain.fth:1:1: stack-underflow\n"
+        );
+    }
+
+    #[test]
+    fn test30_keep_going_and_reset_stack() {
+        let mut interpreter = create_interpreter();
+        interpreter.enable_keep_going(BorthRecovery::ResetStack);
+        let (stack, output) = interpreter.run_code("1 2 drop +\n3 foo 4\n5 6");
+        assert_eq!(stack, &[5, 6]);
+        assert_eq!(output, "stack-underflow\n?\n2 errors\n");
+    }
+
+    #[test]
+    fn test31_keep_going_and_keep_stack() {
+        let mut interpreter = create_interpreter();
+        interpreter.enable_keep_going(BorthRecovery::KeepStack);
+        let (stack, output) = interpreter.run_code("1 2 0 / 3\n4 .");
+        assert_eq!(stack, &[1]);
+        assert_eq!(output, "division-by-zero\n4\n1 error\n");
+    }
+
+    #[test]
+    fn test32_keep_going_across_multiline_definition() {
+        let mut interpreter = create_interpreter();
+        interpreter.enable_keep_going(BorthRecovery::ResetStack);
+        let (stack, output) = interpreter.run_code("drop\n: f\n 1 +\n ;\n2 f");
+        assert_eq!(stack, &[3]);
+        assert_eq!(output, "stack-underflow\n1 error\n");
+    }
+
+    #[test]
+    fn test33_keep_going_drops_definitions_on_failed_line() {
+        let mut interpreter = create_interpreter();
+        interpreter.enable_keep_going(BorthRecovery::ResetStack);
+        let (stack, output) = interpreter.run_code("drop : f 42 ;\nf");
+        assert_eq!(stack, &[] as &[BorthItem]);
+        assert_eq!(output, "stack-underflow\n?\n2 errors\n");
+    }
+
+    #[test]
+    fn test34_stream_output_to_sink() {
+        let mut interpreter = create_interpreter();
+        interpreter.set_output(Box::new(BorthWriter::new(Cursor::new([0u8; 2]))));
+        let (stack, output) = interpreter.run_code("1 2 3 . . .");
+        assert_eq!(stack, &[1]);
+        assert_eq!(output, "");
+        assert_eq!(
+            interpreter.last_error(),
+            Some(&BorthError::CanNotWriteToOutput)
+        );
+    }
+
+    #[test]
+    fn test35_eval_returns_error_without_output() {
+        let mut interpreter = create_interpreter();
+        assert_eq!(
+            interpreter.eval("1 2 . foo"),
+            Err(BorthError::UnknownWord("foo".into()))
+        );
+        assert_eq!(interpreter.stack_items(), &[1]);
+        assert_eq!(interpreter.take_output(), "2");
+        assert_eq!(interpreter.eval("3 ."), Ok(()));
+        assert_eq!(interpreter.take_output(), "3");
+    }
+
+    #[test]
+    fn test36_push_and_pop() {
+        let mut interpreter = create_interpreter();
+        assert_eq!(interpreter.push(4), Ok(()));
+        assert_eq!(interpreter.eval("dup +"), Ok(()));
+        assert_eq!(interpreter.pop(), Ok(8));
+        assert_eq!(interpreter.pop(), Err(BorthError::StackUnderflow));
+    }
+
+    #[test]
+    fn test37_reset() {
+        let mut interpreter = create_interpreter();
+        let _ = interpreter.eval(": foo 1 ; foo foo .");
+        interpreter.reset();
+        assert_eq!(interpreter.stack_items(), &[]);
+        assert_eq!(interpreter.output(), "");
+        assert_eq!(
+            interpreter.eval("foo"),
+            Err(BorthError::UnknownWord("foo".into()))
+        );
+    }
+
+    #[test]
+    fn test38_register_word_with_captured_state() {
+        let mut interpreter = create_interpreter();
+        let calls = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&calls);
+        let result = interpreter.register_word("tick", move |ctx| {
+            counter.set(counter.get() + 1);
+            ctx.push_value(counter.get())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(interpreter.eval(": twice tick tick ; twice TICK"), Ok(()));
+        assert_eq!(interpreter.stack_items(), &[1, 2, 3]);
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn test39_register_word_shadowing() {
+        let mut interpreter = create_interpreter();
+        let _ = interpreter.eval(": old-dup dup ;");
+        let _ = interpreter.register_word("dup", |ctx| ctx.push_value(0));
+        assert_eq!(interpreter.eval("5 old-dup dup"), Ok(()));
+        assert_eq!(interpreter.stack_items(), &[5, 5, 0]);
+        let _ = interpreter.eval(": dup 7 ;");
+        assert_eq!(interpreter.eval("dup"), Ok(()));
+        assert_eq!(interpreter.stack_items(), &[5, 5, 0, 7]);
+    }
+
+    #[test]
+    fn test40_register_invalid_word() {
+        let mut interpreter = create_interpreter();
+        let result = interpreter.register_word("12", |_| Ok(()));
+        assert_eq!(result, Err(BorthError::InvalidWord));
+    }
+
+    #[test]
+    fn test41_budget_exhausted() {
+        let mut interpreter = create_interpreter();
+        interpreter.set_max_steps(Some(4));
+        let (stack, output) = interpreter.run_code(": f 1 2 3 ; f 4");
+        assert_eq!(stack, &[1, 2]);
+        assert_eq!(output, "budget-exhausted\n");
+        assert_eq!(interpreter.steps_left(), Some(0));
+    }
+
+    #[test]
+    fn test42_output_limit() {
+        let mut interpreter = create_interpreter();
+        interpreter.set_limits(BorthLimits {
+            max_output: Some(4),
+            ..Default::default()
+        });
+        let (_, output) = interpreter.run_code("1 2 3 . . .");
+        assert_eq!(output, "3 2 output-limit-exceeded\n");
+    }
+
+    #[test]
+    fn test43_word_limit() {
+        let mut interpreter = create_interpreter();
+        interpreter.set_limits(BorthLimits {
+            max_words: Some(2),
+            ..Default::default()
+        });
+        let (stack, output) = interpreter.run_code(": a 1 ; : a 2 ; : b 3 ; a");
+        assert_eq!(stack, &[]);
+        assert_eq!(output, "word-limit-exceeded\n");
+    }
+
+    #[test]
+    fn test44_code_limit() {
+        let mut interpreter = create_interpreter();
+        interpreter.set_limits(BorthLimits {
+            max_code: Some(4),
+            ..Default::default()
+        });
+        let (_, output) = interpreter.run_code(": a if 1 else 2 then ; : b a a ;");
+        assert_eq!(output, "code-limit-exceeded\n");
+        interpreter.reset();
+        let (_, output) = interpreter.run_code(": a 1 2 3 4 5 ;");
+        assert_eq!(output, "code-limit-exceeded\n");
+    }
+
+    #[test]
+    fn test45_sandbox_denies_output() {
+        let sandbox = BorthSandbox {
+            output: true,
+            ..Default::default()
+        };
+        let mut interpreter = BorthInterpreter::with_sandbox(1024, sandbox);
+        interpreter.enable_keep_going(BorthRecovery::KeepStack);
+        let code = "1 .\n65 EMIT\n.\" hi\" 2\ncr\n: . drop ; 3 .";
+        let (stack, output) = interpreter.run_code(code);
+        assert_eq!(stack, &[1, 65]);
+        assert_eq!(
+            output,
+            "forbidden-word\nforbidden-word\nforbidden-word\nforbidden-word\n4 errors\n"
+        );
+        interpreter.reset();
+        let (_, output) = interpreter.run_code("1 .");
+        assert!(output.starts_with("forbidden-word"));
+    }
+
+    #[test]
+    fn test46_bye_stops_without_report() {
+        let mut interpreter = create_interpreter();
+        interpreter.enable_keep_going(BorthRecovery::KeepStack);
+        let (stack, output) = interpreter.run_code("1 2 3 bye-status 4\n5");
+        assert_eq!(stack, &[1, 2]);
+        assert_eq!(output, "0 errors\n");
+        assert_eq!(interpreter.last_error(), Some(&BorthError::Bye(3)));
+
+        let (stack, _) = interpreter.run_code("3 4 + bye 5");
+        assert_eq!(stack, &[1, 2, 7]);
+        assert_eq!(interpreter.last_error(), Some(&BorthError::Bye(0)));
+    }
+
+    #[test]
+    fn test47_last_error_location() {
+        let mut interpreter = create_interpreter();
+        interpreter.enable_keep_going(BorthRecovery::KeepStack);
+        interpreter.run_code(": foo 1 + ;\n1 2\n  drop drop foo\n3");
+        assert_eq!(interpreter.last_error(), Some(&BorthError::StackUnderflow));
+        assert_eq!(
+            interpreter.last_error_position(),
+            Some(BorthPosition::new(8, 1, 9))
+        );
+        assert_eq!(interpreter.last_error_word(), Some("foo"));
+        assert_eq!(interpreter.stats().max_stack_depth, 2);
+
+        interpreter.run_code("4");
+        assert_eq!(interpreter.last_error_position(), None);
+    }
+
+    #[test]
+    fn test48_check_without_running() {
+        let mut interpreter = create_interpreter();
+        let issues = interpreter.check(": foo if 1 then ;\n: bar + ;\n1 bar .");
+        let error = BorthError::InconsistentStackEffect;
+        let inconsistent = BorthIssue::new(error, BorthPosition::new(6, 1, 7), Some("foo"));
+        let underflow = BorthIssue::new(
+            BorthError::StackUnderflow,
+            BorthPosition::new(30, 3, 3),
+            Some("bar"),
+        );
+        assert_eq!(issues, [inconsistent, underflow]);
+        assert_eq!(interpreter.stack_items(), &[]);
+        assert_eq!(
+            interpreter.stack_effect("bar"),
+            Some(BorthEffect::new(2, 1))
+        );
+        assert_eq!(
+            interpreter.stack_effect("dup"),
+            Some(BorthEffect::new(1, 2))
+        );
+
+        assert_eq!(interpreter.push(1), Ok(()));
+        assert_eq!(interpreter.check("1 bar ."), []);
+    }
+
+    #[test]
+    fn test49_diagnose_without_running() {
+        let mut interpreter = create_interpreter();
+        let code = ": foo if bar then ;\nfoo : baz 1\n+ if";
+        let issues = interpreter.diagnose(code);
+        let unknown = BorthError::UnknownWord("bar".into());
+        let incomplete = BorthError::IncompleteStatement;
+        assert_eq!(
+            issues,
+            [
+                BorthIssue::new(unknown, BorthPosition::new(9, 1, 10), Some("foo")),
+                BorthIssue::new(incomplete, BorthPosition::new(24, 2, 5), None),
+            ]
+        );
+        assert_eq!(interpreter.stack_items(), &[]);
+        let definition = interpreter.definition("foo").map(|d| d.source(code));
+        assert_eq!(definition, Some(Some("foo if bar then ;")));
+        assert_eq!(interpreter.definition("dup"), None);
+    }
+
+    #[test]
+    fn test50_hook_sees_each_step() {
+        let steps = Rc::new(RefCell::new(vec![]));
+        let mut interpreter = create_interpreter();
+        interpreter.set_hook(Some(Box::new(StepRecorder(Rc::clone(&steps)))));
+        interpreter.run_code(": sq dup * ;\n3 sq bye 4");
+        assert_eq!(
+            steps.borrow().as_slice(),
+            [
+                ": 0 []",
+                "3 0 []",
+                "sq 0 [3]",
+                "dup 1 [3]",
+                "* 1 [3, 3]",
+                "bye 0 [9]",
+                "end [9]"
+            ]
+        );
+        assert_eq!(interpreter.last_error(), Some(&BorthError::Bye(3)));
+        assert_eq!(interpreter.stats().steps, 5);
+
+        interpreter.set_hook(None);
+        interpreter.run_code("4");
+        assert_eq!(steps.borrow().len(), 7);
+    }
+
+    #[test]
+    fn test51_comments_are_skipped() {
+        run_code_and_assert_stack_equals(": foo ( a -- b ) 1 ( one ) + ; ( x ) 2 foo", &[3]);
+        run_code_and_assert_output_equals("1 ( not closed", "incomplete-statement\n");
+    }
+
+    #[test]
+    fn test52_strict_mode_checks_declared_effects() {
+        let mut interpreter = create_interpreter();
+        interpreter.enable_strict();
+        interpreter.run_code(": foo ( a b -- c ) + ; : bar ( a -- b c ) 1 2 ;\n1 2 foo");
+        assert_eq!(interpreter.output(), "stack-effect-mismatch bar\n");
+        assert!(!interpreter.words().contains(&"bar"));
+        assert_eq!(interpreter.stack_items(), &[]);
+
+        interpreter.take_output();
+        interpreter.run_code("1 2 foo");
+        assert_eq!(interpreter.stack_items(), &[3]);
+    }
+
+    #[test]
+    fn test53_strict_mode_checks_unknown_effects_at_runtime() {
+        let mut interpreter = create_interpreter();
+        interpreter.enable_strict();
+        assert_eq!(
+            interpreter.register_word("host", |ctx| ctx.push_value(7)),
+            Ok(())
+        );
+        interpreter.enable_diagnostics();
+        interpreter.set_source("main.fth");
+        interpreter.run_code(": foo ( -- a ) host ; : bar ( -- ) host ;\nfoo bar");
+        assert_eq!(
+            interpreter.output(),
+            "main.fth:1:36: stack-effect-mismatch bar in word bar\n"
+        );
+        assert_eq!(interpreter.stack_items(), &[7, 7]);
+    }
+
+    #[test]
+    fn test54_errors_in_the_source_that_defined_the_word() {
+        let mut interpreter = create_interpreter();
+        interpreter.enable_diagnostics();
+        interpreter.enable_backtrace();
+        interpreter.set_source("lib.fth");
+        interpreter.run_code("\n: foo\n  + ;");
+        interpreter.set_source("<eval>");
+        let (_, output) = interpreter.run_code("foo");
+        assert_eq!(
+            output,
+            concat!(
+                "lib.fth:3:3: stack-underflow in word foo\n",
+                "Backtrace:\n  foo\nAt: lib.fth:3:3\nStack: <0>\n"
+            )
+        );
+        assert_eq!(interpreter.last_error_source(), Some("lib.fth"));
+        interpreter.run_code("drop");
+        assert_eq!(interpreter.last_error_source(), Some("<eval>"));
+    }
+
+    #[test]
+    fn test55_call_word_named_open_paren() {
+        let mut interpreter = create_interpreter();
+        interpreter.run_code("( a comment ) : ( 42 ;");
+        let (_, output) = interpreter.run_code("( . ( .");
+        assert_eq!(output, "42 42");
+        assert_eq!(interpreter.last_error(), None);
+    }
+
     #[test]
     fn test_non_transitive() {
         run_code_and_assert_stack_equals(
@@ -0,0 +1,61 @@
+use super::{
+    diagnostic::BorthDiagnostic, errors::BorthError, expression::BorthExpression, node::BorthNode,
+    position::BorthPosition,
+};
+
+#[derive(Debug, PartialEq)]
+/// Error the checker found in some code without running it
+pub struct BorthIssue {
+    /// The error the code would fail with
+    pub error: BorthError,
+    /// Where the code fails
+    pub position: BorthPosition,
+    /// The word the error is in or comes from, if any
+    pub word: Option<String>,
+}
+
+impl BorthIssue {
+    /// Create a new BorthIssue instance
+    pub fn new(error: BorthError, position: BorthPosition, word: Option<&str>) -> Self {
+        Self {
+            error,
+            position,
+            word: word.map(str::to_string),
+        }
+    }
+
+    /// Describe the issue like the diagnostics of a failed run of the source
+    pub fn describe(&self, source: &str) -> String {
+        let word = self.word.as_deref();
+        BorthDiagnostic::new(source, &self.error, Some(self.position), word).to_string()
+    }
+}
+
+/// Add an issue for each error the parser left in the nodes or the blocks of their
+/// conditionals, in the given word if any
+pub fn push_parse_errors(nodes: &[BorthNode], word: Option<&str>, issues: &mut Vec<BorthIssue>) {
+    for node in nodes {
+        if let Some(error) = node.expression().parse_error() {
+            issues.push(BorthIssue::new(error, node.position(), word));
+        }
+        if let BorthExpression::IfElseThen(if_block, else_block) = node.expression() {
+            push_parse_errors(if_block, word, issues);
+            push_parse_errors(else_block, word, issues);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_describe_with_source_and_word() {
+        let position = BorthPosition::new(4, 1, 5);
+        let issue = BorthIssue::new(BorthError::StackUnderflow, position, Some("foo"));
+        assert_eq!(
+            issue.describe("main.fth"),
+            "main.fth:1:5: stack-underflow in word foo"
+        );
+    }
+}
@@ -0,0 +1,185 @@
+mod reader;
+
+use borth::BorthResult;
+use reader::BorthJsonReader;
+use std::fmt::{Display, Formatter, Result, Write};
+
+/// JSON value written by the `--format=json` mode and exchanged with editors
+#[derive(Debug, PartialEq)]
+pub enum BorthJson {
+    Null,
+    Bool(bool),
+    Number(i128),
+    String(String),
+    Array(Vec<BorthJson>),
+    Object(Vec<(String, BorthJson)>),
+}
+
+impl BorthJson {
+    /// Parse a JSON document. Numbers with a fraction or an exponent are truncated.
+    pub fn parse(text: &str) -> BorthResult<BorthJson> {
+        BorthJsonReader::new(text).read_document()
+    }
+
+    /// Create an object with the given fields, in order
+    pub fn object(fields: Vec<(&str, BorthJson)>) -> BorthJson {
+        let fields = fields.into_iter();
+        BorthJson::Object(
+            fields
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    /// Return the value, or `Null` if there is none
+    pub fn from_option<T>(value: Option<T>, map: impl FnOnce(T) -> BorthJson) -> BorthJson {
+        value.map_or(BorthJson::Null, map)
+    }
+
+    /// Return the value of the field, if this is an object that has it
+    pub fn get(&self, key: &str) -> Option<&BorthJson> {
+        match self {
+            BorthJson::Object(fields) => fields
+                .iter()
+                .find(|(field, _)| field == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Take the value of the field out of this object, if it has it
+    pub fn remove(&mut self, key: &str) -> Option<BorthJson> {
+        match self {
+            BorthJson::Object(fields) => {
+                let index = fields.iter().position(|(field, _)| field == key)?;
+                Some(fields.remove(index).1)
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the string, if this is one
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BorthJson::String(str) => Some(str),
+            _ => None,
+        }
+    }
+
+    /// Return the number, if this is one
+    pub fn as_number(&self) -> Option<i128> {
+        match self {
+            BorthJson::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Return the items, if this is an array
+    pub fn as_array(&self) -> Option<&[BorthJson]> {
+        match self {
+            BorthJson::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl Display for BorthJson {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            BorthJson::Null => f.write_str("null"),
+            BorthJson::Bool(value) => write!(f, "{}", value),
+            BorthJson::Number(value) => write!(f, "{}", value),
+            BorthJson::String(str) => write_string(f, str),
+            BorthJson::Array(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_char(']')
+            }
+            BorthJson::Object(fields) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+/// Write the string quoted, escaping quotes, backslashes and control characters
+fn write_string(f: &mut Formatter<'_>, str: &str) -> Result {
+    f.write_char('"')?;
+    for c in str.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_scalars() {
+        assert_eq!(BorthJson::Null.to_string(), "null");
+        assert_eq!(BorthJson::Number(-12).to_string(), "-12");
+        assert_eq!(
+            BorthJson::from_option(Some(3), BorthJson::Number).to_string(),
+            "3"
+        );
+    }
+
+    #[test]
+    fn test2_escape_strings() {
+        let json = BorthJson::String("a \"b\"\\\n\t\u{1}é".into());
+        assert_eq!(json.to_string(), r#""a \"b\"\\\n\t\u0001é""#);
+    }
+
+    #[test]
+    fn test3_nested_values() {
+        let json = BorthJson::object(vec![
+            (
+                "stack",
+                BorthJson::Array(vec![BorthJson::Number(1), BorthJson::Number(2)]),
+            ),
+            ("empty", BorthJson::Array(vec![])),
+            ("error", BorthJson::object(vec![("word", BorthJson::Null)])),
+        ]);
+        assert_eq!(
+            json.to_string(),
+            r#"{"stack":[1,2],"empty":[],"error":{"word":null}}"#
+        );
+    }
+
+    #[test]
+    fn test4_parse_and_read_fields() {
+        let text = r#"{"id":1,"ok":true,"params":{"text":"a\"b"},"items":[null]}"#;
+        let json = BorthJson::parse(text);
+        assert_eq!(json.as_ref().map(ToString::to_string), Ok(text.to_string()));
+        let json = json.unwrap_or(BorthJson::Null);
+        assert_eq!(json.get("id").and_then(BorthJson::as_number), Some(1));
+        let params = json.get("params");
+        let text = params.and_then(|params| params.get("text"));
+        assert_eq!(text.and_then(BorthJson::as_str), Some("a\"b"));
+        let items = json.get("items").and_then(BorthJson::as_array);
+        assert_eq!(items, Some([BorthJson::Null].as_slice()));
+        assert_eq!(json.get("missing"), None);
+    }
+}
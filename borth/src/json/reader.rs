@@ -0,0 +1,222 @@
+use super::BorthJson;
+use borth::{BorthError, BorthResult};
+use std::{iter::Peekable, str::Chars};
+
+/// Read a JSON document one character at a time, failing with `InvalidMessage`
+/// when it is not valid
+pub struct BorthJsonReader<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> BorthJsonReader<'a> {
+    /// Create a new BorthJsonReader instance at the start of the text
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+        }
+    }
+
+    /// Read a value that takes the whole text
+    pub fn read_document(&mut self) -> BorthResult<BorthJson> {
+        let value = self.read_value()?;
+        self.skip_whitespace();
+        match self.chars.next() {
+            None => Ok(value),
+            Some(_) => Err(BorthError::InvalidMessage),
+        }
+    }
+
+    /// Read the next value
+    pub fn read_value(&mut self) -> BorthResult<BorthJson> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('{') => self.read_object(),
+            Some('[') => self.read_array(),
+            Some('"') => self.read_string().map(BorthJson::String),
+            Some('t') => self.read_literal("true", BorthJson::Bool(true)),
+            Some('f') => self.read_literal("false", BorthJson::Bool(false)),
+            Some('n') => self.read_literal("null", BorthJson::Null),
+            Some(_) => self.read_number(),
+            None => Err(BorthError::InvalidMessage),
+        }
+    }
+
+    fn read_object(&mut self) -> BorthResult<BorthJson> {
+        self.expect('{')?;
+        let mut fields = vec![];
+        if self.next_is('}') {
+            return Ok(BorthJson::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.read_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            fields.push((key, self.read_value()?));
+            if self.next_is('}') {
+                return Ok(BorthJson::Object(fields));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn read_array(&mut self) -> BorthResult<BorthJson> {
+        self.expect('[')?;
+        let mut items = vec![];
+        if self.next_is(']') {
+            return Ok(BorthJson::Array(items));
+        }
+        loop {
+            items.push(self.read_value()?);
+            if self.next_is(']') {
+                return Ok(BorthJson::Array(items));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn read_string(&mut self) -> BorthResult<String> {
+        self.expect('"')?;
+        let mut str = String::new();
+        loop {
+            match self.chars.next().ok_or(BorthError::InvalidMessage)? {
+                '"' => return Ok(str),
+                '\\' => str.push(self.read_escape()?),
+                c => str.push(c),
+            }
+        }
+    }
+
+    /// Read the character escaped after a backslash
+    fn read_escape(&mut self) -> BorthResult<char> {
+        match self.chars.next().ok_or(BorthError::InvalidMessage)? {
+            '"' => Ok('"'),
+            '\\' => Ok('\\'),
+            '/' => Ok('/'),
+            'b' => Ok('\u{8}'),
+            'f' => Ok('\u{c}'),
+            'n' => Ok('\n'),
+            'r' => Ok('\r'),
+            't' => Ok('\t'),
+            'u' => self.read_code_point(),
+            _ => Err(BorthError::InvalidMessage),
+        }
+    }
+
+    /// Read a `\u` escape, joining surrogate pairs
+    fn read_code_point(&mut self) -> BorthResult<char> {
+        let high = self.read_hex()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high).ok_or(BorthError::InvalidMessage);
+        }
+        self.expect('\\')?;
+        self.expect('u')?;
+        let low = self.read_hex()?;
+        if !(0xdc00..0xe000).contains(&low) {
+            return Err(BorthError::InvalidMessage);
+        }
+        let code_point = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+        char::from_u32(code_point).ok_or(BorthError::InvalidMessage)
+    }
+
+    fn read_hex(&mut self) -> BorthResult<u32> {
+        let digits: String = self.chars.by_ref().take(4).collect();
+        match digits.len() {
+            4 => u32::from_str_radix(&digits, 16).or(Err(BorthError::InvalidMessage)),
+            _ => Err(BorthError::InvalidMessage),
+        }
+    }
+
+    fn read_number(&mut self) -> BorthResult<BorthJson> {
+        let mut text = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|c| "+-.eE".contains(*c) || c.is_ascii_digit())
+        {
+            text.push(c);
+        }
+        if let Ok(value) = text.parse::<i128>() {
+            return Ok(BorthJson::Number(value));
+        }
+        match text.parse::<f64>() {
+            Ok(value) if value.is_finite() => Ok(BorthJson::Number(value as i128)),
+            _ => Err(BorthError::InvalidMessage),
+        }
+    }
+
+    fn read_literal(&mut self, literal: &str, value: BorthJson) -> BorthResult<BorthJson> {
+        for expected in literal.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    /// Skip the whitespace and consume the next character if it is the given one
+    fn next_is(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        self.chars.next_if_eq(&expected).is_some()
+    }
+
+    fn expect(&mut self, expected: char) -> BorthResult<()> {
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(BorthError::InvalidMessage),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> BorthResult<BorthJson> {
+        BorthJsonReader::new(text).read_document()
+    }
+
+    #[test]
+    fn test1_read_scalars() {
+        assert_eq!(parse(" true "), Ok(BorthJson::Bool(true)));
+        assert_eq!(parse("null"), Ok(BorthJson::Null));
+        assert_eq!(parse("-12"), Ok(BorthJson::Number(-12)));
+        assert_eq!(parse("2.5e1"), Ok(BorthJson::Number(25)));
+    }
+
+    #[test]
+    fn test2_read_escapes() {
+        let json = parse(r#""a\n\u00e9\ud83d\ude00\/""#);
+        assert_eq!(json, Ok(BorthJson::String("a\né😀/".into())));
+    }
+
+    #[test]
+    fn test3_read_nested_values() {
+        let json = parse(r#"{ "a" : [1, {}], "b": [] }"#);
+        let expected = BorthJson::object(vec![
+            (
+                "a",
+                BorthJson::Array(vec![BorthJson::Number(1), BorthJson::Object(vec![])]),
+            ),
+            ("b", BorthJson::Array(vec![])),
+        ]);
+        assert_eq!(json, Ok(expected));
+    }
+
+    #[test]
+    fn test4_reject_invalid_documents() {
+        for text in [
+            "",
+            "{",
+            "[1,]",
+            "\"open",
+            "tru",
+            "1 2",
+            "{1:2}",
+            r#""\ud800""#,
+        ] {
+            assert_eq!(parse(text), Err(BorthError::InvalidMessage), "{}", text);
+        }
+    }
+}
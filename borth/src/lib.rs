@@ -0,0 +1,66 @@
+//! Borth is an interpreter for a subset of Forth-79 with a stack of 16-bit signed integers.
+//!
+//! The [`BorthInterpreter`] keeps its dictionary and data stack between evaluations,
+//! so a host application can feed it code, inspect or push stack values and collect
+//! the output it produced.
+//!
+//! ```
+//! use borth::{BorthError, BorthInterpreter};
+//!
+//! let mut interpreter = BorthInterpreter::new();
+//! interpreter.eval(": SQUARE DUP * ;")?;
+//! interpreter.push(7)?;
+//! interpreter.eval("SQUARE DUP .")?;
+//! assert_eq!(interpreter.stack_items(), &[49]);
+//! assert_eq!(interpreter.take_output(), "49");
+//!
+//! assert_eq!(interpreter.eval("DROP DROP"), Err(BorthError::StackUnderflow));
+//! # Ok::<(), BorthError>(())
+//! ```
+
+mod backtrace;
+mod callgraph;
+mod checker;
+mod context;
+mod definition;
+mod diagnostic;
+mod dict;
+mod effect;
+mod errors;
+mod expression;
+mod formatter;
+mod hook;
+mod image;
+mod interpreter;
+mod issue;
+mod limits;
+mod native;
+mod node;
+mod parser;
+mod position;
+mod recovery;
+mod sandbox;
+mod sink;
+mod stack_view;
+mod stats;
+mod step;
+mod token;
+mod transpiler;
+mod vm;
+
+pub use context::{BorthContext, BorthItem};
+pub use definition::BorthDefinition;
+pub use effect::BorthEffect;
+pub use errors::{BorthError, BorthResult};
+pub use formatter::BorthFormatter;
+pub use hook::BorthHook;
+pub use interpreter::BorthInterpreter;
+pub use issue::BorthIssue;
+pub use limits::BorthLimits;
+pub use position::BorthPosition;
+pub use recovery::BorthRecovery;
+pub use sandbox::BorthSandbox;
+pub use sink::{BorthSink, buffer::BorthBuffer, writer::BorthWriter};
+pub use stack_view::BorthStackView;
+pub use stats::BorthStats;
+pub use step::BorthStep;
@@ -0,0 +1,10 @@
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+/// Caps on the resources a program can take. `None` means unlimited.
+pub struct BorthLimits {
+    /// Total bytes of output produced by the program
+    pub max_output: Option<usize>,
+    /// Number of word definitions, counting redefinitions
+    pub max_words: Option<usize>,
+    /// Total number of expressions compiled into word bodies
+    pub max_code: Option<usize>,
+}
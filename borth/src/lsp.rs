@@ -0,0 +1,294 @@
+mod document;
+mod reply;
+mod transport;
+
+use super::json::BorthJson;
+use borth::{BorthError, BorthInterpreter, BorthResult};
+use document::BorthDocument;
+use reply::BorthReply;
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+const FULL_SYNC: i128 = 1;
+const FUNCTION_KIND: i128 = 3;
+const PARSE_ERROR: i128 = -32700;
+const METHOD_NOT_FOUND: i128 = -32601;
+
+/// Language server for editors, speaking JSON-RPC over a reader and a writer.
+/// It reports what the parser rejects, describes words on hover, finds where
+/// words were defined with `:` and completes the words of the dictionary.
+pub struct BorthLsp {
+    documents: HashMap<String, BorthDocument>,
+}
+
+impl BorthLsp {
+    /// Create a new BorthLsp instance with no open documents
+    pub fn new() -> Self {
+        Self {
+            documents: HashMap::new(),
+        }
+    }
+
+    /// Answer the messages until `exit` or the end of the input
+    pub fn start(&mut self, reader: &mut impl BufRead, writer: &mut impl Write) -> BorthResult<()> {
+        while let Some(text) = transport::read_message(reader)? {
+            let Ok(mut message) = BorthJson::parse(&text) else {
+                let message = BorthError::InvalidMessage.to_string();
+                let error = BorthReply::Error(PARSE_ERROR, message).into_message(BorthJson::Null);
+                transport::write_message(writer, &error)?;
+                continue;
+            };
+            let method = message.get("method").and_then(BorthJson::as_str);
+            let method = method.unwrap_or_default().to_string();
+            if method == "exit" {
+                break;
+            }
+            if let Some(reply) = self.handle(&method, &message) {
+                let id = message.remove("id").unwrap_or(BorthJson::Null);
+                transport::write_message(writer, &reply.into_message(id))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle a request or a notification and return what to send back, if anything
+    fn handle(&mut self, method: &str, message: &BorthJson) -> Option<BorthReply> {
+        let params = message.get("params").unwrap_or(&BorthJson::Null);
+        let reply = match method {
+            "initialize" => BorthReply::Result(capabilities()),
+            "shutdown" => BorthReply::Result(BorthJson::Null),
+            "textDocument/hover" => BorthReply::Result(self.hover(params)),
+            "textDocument/definition" => BorthReply::Result(self.definition(params)),
+            "textDocument/completion" => BorthReply::Result(self.completion(params)),
+            "textDocument/didOpen" | "textDocument/didChange" | "textDocument/didClose" => {
+                return self.sync(method, params);
+            }
+            _ => BorthReply::Error(METHOD_NOT_FOUND, format!("method-not-found {}", method)),
+        };
+        message.get("id").and(Some(reply))
+    }
+
+    /// Keep the text of the documents the editor opens and changes, and publish
+    /// the diagnostics of the new text
+    fn sync(&mut self, method: &str, params: &BorthJson) -> Option<BorthReply> {
+        let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+        let text = match method {
+            "textDocument/didOpen" => params.get("textDocument")?.get("text")?.as_str()?,
+            "textDocument/didChange" => {
+                let changes = params.get("contentChanges")?.as_array()?;
+                changes.last()?.get("text")?.as_str()?
+            }
+            _ => {
+                self.documents.remove(uri);
+                return Some(publish(uri, BorthJson::Array(vec![])));
+            }
+        };
+        let document = BorthDocument::new(text.to_string());
+        let (_, issues) = document.analyze();
+        let diagnostics = document.diagnostics(&issues);
+        self.documents.insert(uri.to_string(), document);
+        Some(publish(uri, diagnostics))
+    }
+
+    /// Describe the word under the cursor with its definition and stack effect
+    fn hover(&self, params: &BorthJson) -> BorthJson {
+        let Some((document, start, word)) = self.word_at(params) else {
+            return BorthJson::Null;
+        };
+        let (interpreter, _) = document.analyze();
+        let definition = interpreter
+            .definition(word)
+            .and_then(|d| d.source(document.text()));
+        let lines = [
+            definition.map(|source| format!(": {}", source)),
+            interpreter
+                .stack_effect(word)
+                .map(|effect| effect.to_string()),
+        ];
+        let lines: Vec<String> = lines.into_iter().flatten().collect();
+        if lines.is_empty() {
+            return BorthJson::Null;
+        }
+        let contents = BorthJson::object(vec![
+            ("kind", string("plaintext")),
+            ("value", BorthJson::String(lines.join("\n"))),
+        ]);
+        BorthJson::object(vec![
+            ("contents", contents),
+            ("range", document.range(start, document.word_end(start))),
+        ])
+    }
+
+    /// Return where the word under the cursor was defined with `:`
+    fn definition(&self, params: &BorthJson) -> BorthJson {
+        let Some((document, _, word)) = self.word_at(params) else {
+            return BorthJson::Null;
+        };
+        let (interpreter, _) = document.analyze();
+        let Some(definition) = interpreter.definition(word) else {
+            return BorthJson::Null;
+        };
+        let start = definition.position.offset;
+        let uri = params.get("textDocument").and_then(|d| d.get("uri"));
+        BorthJson::object(vec![
+            (
+                "uri",
+                BorthJson::from_option(uri.and_then(BorthJson::as_str), string),
+            ),
+            ("range", document.range(start, document.word_end(start))),
+        ])
+    }
+
+    /// List the words of the dictionary after parsing the document
+    fn completion(&self, params: &BorthJson) -> BorthJson {
+        let interpreter = match self.document(params) {
+            Some(document) => document.analyze().0,
+            None => BorthInterpreter::new(),
+        };
+        let items = interpreter.words().into_iter().map(|word| {
+            let effect = interpreter
+                .stack_effect(word)
+                .map(|effect| effect.to_string());
+            BorthJson::object(vec![
+                ("label", string(word)),
+                ("kind", BorthJson::Number(FUNCTION_KIND)),
+                ("detail", BorthJson::from_option(effect, BorthJson::String)),
+            ])
+        });
+        BorthJson::Array(items.collect())
+    }
+
+    fn document(&self, params: &BorthJson) -> Option<&BorthDocument> {
+        let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+        self.documents.get(uri)
+    }
+
+    fn word_at(&self, params: &BorthJson) -> Option<(&BorthDocument, usize, &str)> {
+        let document = self.document(params)?;
+        let (start, word) = document.word_at(params.get("position")?)?;
+        Some((document, start, word))
+    }
+}
+
+fn publish(uri: &str, diagnostics: BorthJson) -> BorthReply {
+    let params = BorthJson::object(vec![("uri", string(uri)), ("diagnostics", diagnostics)]);
+    BorthReply::Notification("textDocument/publishDiagnostics", params)
+}
+
+/// Describe what the server can do, in answer to `initialize`
+fn capabilities() -> BorthJson {
+    let capabilities = BorthJson::object(vec![
+        ("textDocumentSync", BorthJson::Number(FULL_SYNC)),
+        ("hoverProvider", BorthJson::Bool(true)),
+        ("definitionProvider", BorthJson::Bool(true)),
+        ("completionProvider", BorthJson::object(vec![])),
+    ]);
+    let info = BorthJson::object(vec![
+        ("name", string("borth")),
+        ("version", string(env!("CARGO_PKG_VERSION"))),
+    ]);
+    BorthJson::object(vec![("capabilities", capabilities), ("serverInfo", info)])
+}
+
+/// Create a JSON string
+pub fn string(str: &str) -> BorthJson {
+    BorthJson::String(str.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const URI: &str = "file:///main.fth";
+
+    fn frame(message: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{}", message.len(), message)
+    }
+
+    fn request(id: usize, method: &str, line: usize, character: usize) -> String {
+        frame(&format!(
+            r#"{{"jsonrpc":"2.0","id":{},"method":"{}","params":{{"textDocument":{{"uri":"{}"}},"position":{{"line":{},"character":{}}}}}}}"#,
+            id, method, URI, line, character
+        ))
+    }
+
+    fn run_session(messages: &[String]) -> String {
+        let mut reader = Cursor::new(messages.concat());
+        let mut output = vec![];
+        let result = BorthLsp::new().start(&mut reader, &mut output);
+        assert_eq!(result, Ok(()));
+        String::from_utf8(output).unwrap_or_default()
+    }
+
+    fn open(text: &str) -> String {
+        let text = BorthJson::String(text.into());
+        frame(&format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"{}","text":{}}}}}}}"#,
+            URI, text
+        ))
+    }
+
+    #[test]
+    fn test1_initialize_and_exit() {
+        let output = run_session(&[
+            frame(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#),
+            frame(r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#),
+            frame(r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#),
+            frame(r#"{"jsonrpc":"2.0","method":"exit"}"#),
+            frame(r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#),
+        ]);
+        assert!(output.contains(r#""id":1,"result":{"capabilities":{"textDocumentSync":1,"#));
+        assert!(output.contains(r#"{"jsonrpc":"2.0","id":2,"result":null}"#));
+        assert!(!output.contains(r#""id":3"#));
+    }
+
+    #[test]
+    fn test2_publish_diagnostics() {
+        let output = run_session(&[open(": foo bar ;\n1 if")]);
+        assert!(output.contains(r#""method":"textDocument/publishDiagnostics""#));
+        assert!(output.contains(r#""message":"unknown word bar in word foo""#));
+        assert!(output.contains(
+            r#""range":{"start":{"line":1,"character":2},"end":{"line":1,"character":4}}"#
+        ));
+    }
+
+    #[test]
+    fn test3_hover_definition_and_completion() {
+        let output = run_session(&[
+            open(": square ( n -- n ) dup * ;\n3 square"),
+            request(1, "textDocument/hover", 1, 4),
+            request(2, "textDocument/definition", 1, 4),
+            request(3, "textDocument/completion", 0, 0),
+            request(4, "textDocument/hover", 1, 1),
+        ]);
+        assert!(output.contains(r#""value":": square ( n -- n ) dup * ;\n( 1 -- 1 )""#));
+        assert!(output.contains(&format!(
+            r#""id":2,"result":{{"uri":"{}","range":{{"start":{{"line":0,"character":2}},"end":{{"line":0,"character":8}}}}}}"#,
+            URI
+        )));
+        assert!(output.contains(r#"{"label":"square","kind":3,"detail":"( 1 -- 1 )"}"#));
+        assert!(output.contains(r#"{"label":"dup","kind":3,"detail":"( 1 -- 2 )"}"#));
+        assert!(
+            output.contains(
+                r#""id":4,"result":{"contents":{"kind":"plaintext","value":"( 0 -- 1 )"}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test4_answer_errors() {
+        let output = run_session(&[
+            frame("{"),
+            frame(r#"{"jsonrpc":"2.0","id":"a","method":"workspace/symbol"}"#),
+        ]);
+        assert!(output.contains(
+            r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"invalid-message"}}"#
+        ));
+        assert!(output.contains(
+            r#""id":"a","error":{"code":-32601,"message":"method-not-found workspace/symbol"}"#
+        ));
+    }
+}
@@ -0,0 +1,153 @@
+use crate::json::BorthJson;
+use borth::{BorthError, BorthInterpreter, BorthIssue};
+
+/// Text of a file open in the editor, with the positions the editor uses:
+/// lines from 0 and characters counted in UTF-16 code units
+pub struct BorthDocument {
+    text: String,
+}
+
+impl BorthDocument {
+    /// Create a new BorthDocument instance with the given text
+    pub fn new(text: String) -> Self {
+        Self { text }
+    }
+
+    /// Return the text of the document
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Parse the document into a new interpreter, without running it, and return
+    /// the interpreter with what the parser rejected
+    pub fn analyze(&self) -> (BorthInterpreter, Vec<BorthIssue>) {
+        let mut interpreter = BorthInterpreter::new();
+        let issues = interpreter.diagnose(&self.text);
+        (interpreter, issues)
+    }
+
+    /// Return the issues as editor diagnostics
+    pub fn diagnostics(&self, issues: &[BorthIssue]) -> BorthJson {
+        let diagnostics = issues.iter().map(|issue| {
+            let start = issue.position.offset;
+            BorthJson::object(vec![
+                ("range", self.range(start, self.word_end(start))),
+                ("severity", BorthJson::Number(1)),
+                ("source", BorthJson::String("borth".into())),
+                ("message", BorthJson::String(message(issue))),
+            ])
+        });
+        BorthJson::Array(diagnostics.collect())
+    }
+
+    /// Return the word under the editor position, with the offset where it starts
+    pub fn word_at(&self, position: &BorthJson) -> Option<(usize, &str)> {
+        let offset = self.offset(position)?;
+        let before = self.text.get(..offset)?;
+        let start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = self.text.get(start..self.word_end(start))?;
+        Some((start, word)).filter(|_| !word.is_empty())
+    }
+
+    /// Return the editor range between two byte offsets
+    pub fn range(&self, start: usize, end: usize) -> BorthJson {
+        BorthJson::object(vec![
+            ("start", self.position(start)),
+            ("end", self.position(end)),
+        ])
+    }
+
+    /// Return the offset right after the word that starts at the given one
+    pub fn word_end(&self, start: usize) -> usize {
+        let rest = self.text.get(start..).unwrap_or_default();
+        start + rest.find(char::is_whitespace).unwrap_or(rest.len())
+    }
+
+    /// Return the byte offset of an editor position, if it is in the text
+    fn offset(&self, position: &BorthJson) -> Option<usize> {
+        let line = usize::try_from(position.get("line")?.as_number()?).ok()?;
+        let character = position.get("character")?.as_number()?;
+        let start = match line {
+            0 => 0,
+            _ => self.text.match_indices('\n').nth(line - 1)?.0 + 1,
+        };
+        let mut units = 0;
+        for (i, c) in self.text.get(start..)?.char_indices() {
+            if units >= character || c == '\n' {
+                return Some(start + i);
+            }
+            units += c.len_utf16() as i128;
+        }
+        Some(self.text.len())
+    }
+
+    /// Return the editor position of a byte offset
+    fn position(&self, offset: usize) -> BorthJson {
+        let before = self.text.get(..offset).unwrap_or(&self.text);
+        let line = before.matches('\n').count();
+        let start = before.rfind('\n').map_or(0, |i| i + 1);
+        let character: usize = before
+            .get(start..)
+            .unwrap_or_default()
+            .chars()
+            .map(char::len_utf16)
+            .sum();
+        BorthJson::object(vec![
+            ("line", BorthJson::Number(line as i128)),
+            ("character", BorthJson::Number(character as i128)),
+        ])
+    }
+}
+
+/// Describe the issue for the editor, naming the unknown words
+fn message(issue: &BorthIssue) -> String {
+    let error = match &issue.error {
+        BorthError::UnknownWord(word) => format!("unknown word {}", word),
+        error => error.to_string(),
+    };
+    match &issue.word {
+        Some(word) => format!("{} in word {}", error, word),
+        None => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_position(line: i128, character: i128) -> BorthJson {
+        BorthJson::object(vec![
+            ("line", BorthJson::Number(line)),
+            ("character", BorthJson::Number(character)),
+        ])
+    }
+
+    #[test]
+    fn test1_word_at_position() {
+        let document = BorthDocument::new("1 é\n: foo dup ;".into());
+        assert_eq!(document.word_at(&create_position(1, 3)), Some((7, "foo")));
+        assert_eq!(document.word_at(&create_position(1, 5)), Some((7, "foo")));
+        assert_eq!(document.word_at(&create_position(0, 2)), Some((2, "é")));
+        assert_eq!(document.word_at(&create_position(1, 1)), Some((5, ":")));
+        assert_eq!(document.word_at(&create_position(5, 0)), None);
+    }
+
+    #[test]
+    fn test2_range_in_utf16_units() {
+        let document = BorthDocument::new("😀 x\ny".into());
+        assert_eq!(
+            document.range(5, 8).to_string(),
+            r#"{"start":{"line":0,"character":3},"end":{"line":1,"character":1}}"#
+        );
+    }
+
+    #[test]
+    fn test3_diagnostics_for_parse_errors() {
+        let document = BorthDocument::new(": foo bar ;\nif".into());
+        let (_, issues) = document.analyze();
+        let diagnostics = document.diagnostics(&issues).to_string();
+        assert!(diagnostics.contains(r#""message":"unknown word bar in word foo""#));
+        assert!(diagnostics.contains(r#""message":"incomplete-statement""#));
+        assert!(diagnostics.contains(r#""end":{"line":0,"character":9}"#));
+    }
+}
@@ -0,0 +1,38 @@
+use super::string;
+use crate::json::BorthJson;
+
+/// What the server sends back for a message
+pub enum BorthReply {
+    Result(BorthJson),
+    Error(i128, String),
+    Notification(&'static str, BorthJson),
+}
+
+impl BorthReply {
+    /// Turn the reply into a message, answering the request with the given id
+    pub fn into_message(self, id: BorthJson) -> BorthJson {
+        match self {
+            BorthReply::Result(result) => BorthJson::object(vec![
+                ("jsonrpc", string("2.0")),
+                ("id", id),
+                ("result", result),
+            ]),
+            BorthReply::Error(code, message) => {
+                let error = BorthJson::object(vec![
+                    ("code", BorthJson::Number(code)),
+                    ("message", BorthJson::String(message)),
+                ]);
+                BorthJson::object(vec![
+                    ("jsonrpc", string("2.0")),
+                    ("id", id),
+                    ("error", error),
+                ])
+            }
+            BorthReply::Notification(method, params) => BorthJson::object(vec![
+                ("jsonrpc", string("2.0")),
+                ("method", string(method)),
+                ("params", params),
+            ]),
+        }
+    }
+}
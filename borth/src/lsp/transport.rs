@@ -0,0 +1,89 @@
+use crate::json::BorthJson;
+use borth::{BorthError, BorthResult};
+use std::io::{BufRead, Read, Write};
+
+const CONTENT_LENGTH: &str = "content-length:";
+const MAX_MESSAGE_LEN: u64 = 64 * 1024 * 1024;
+
+/// Read the next message, framed by a `Content-Length` header, and return `None`
+/// at the end of the input. Messages longer than 64 MiB are rejected. The text of
+/// the message is returned as is, so that a message that is not valid JSON can
+/// still be answered.
+pub fn read_message(reader: &mut impl BufRead) -> BorthResult<Option<String>> {
+    let Some(len) = read_headers(reader)? else {
+        return Ok(None);
+    };
+    if len > MAX_MESSAGE_LEN {
+        return Err(BorthError::InvalidMessage);
+    }
+    let mut body = vec![];
+    match reader.take(len).read_to_end(&mut body) {
+        Ok(read) if read as u64 == len => {}
+        _ => return Err(BorthError::CanNotReadCode),
+    }
+    String::from_utf8(body)
+        .map(Some)
+        .or(Err(BorthError::InvalidMessage))
+}
+
+/// Read the headers up to the empty line and return the length of the body
+fn read_headers(reader: &mut impl BufRead) -> BorthResult<Option<u64>> {
+    let mut len = None;
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) if len.is_none() => return Ok(None),
+            Ok(0) | Err(_) => return Err(BorthError::CanNotReadCode),
+            Ok(_) => {}
+        }
+        let header = line.trim_end().to_lowercase();
+        if header.is_empty() {
+            return len.map(Some).ok_or(BorthError::InvalidMessage);
+        }
+        if let Some(value) = header.strip_prefix(CONTENT_LENGTH) {
+            let value = value.trim().parse().or(Err(BorthError::InvalidMessage))?;
+            len = Some(value);
+        }
+    }
+}
+
+/// Write the message with its `Content-Length` header
+pub fn write_message(writer: &mut impl Write, message: &BorthJson) -> BorthResult<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+        .and_then(|_| writer.flush())
+        .or(Err(BorthError::CanNotWriteToOutput))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test1_read_framed_messages() {
+        let input = "Content-Length: 2\r\nContent-Type: x\r\n\r\n{}content-length: 4\r\n\r\nnull";
+        let mut reader = Cursor::new(input);
+        assert_eq!(read_message(&mut reader), Ok(Some("{}".to_string())));
+        assert_eq!(read_message(&mut reader), Ok(Some("null".to_string())));
+        assert_eq!(read_message(&mut reader), Ok(None));
+    }
+
+    #[test]
+    fn test2_reject_messages_without_length() {
+        let mut reader = Cursor::new("Content-Type: x\r\n\r\n{}");
+        assert_eq!(read_message(&mut reader), Err(BorthError::InvalidMessage));
+        let mut reader = Cursor::new("Content-Length: 9\r\n\r\n{}");
+        assert_eq!(read_message(&mut reader), Err(BorthError::CanNotReadCode));
+        let mut reader = Cursor::new("Content-Length: 18446744073709551615\r\n\r\n{}");
+        assert_eq!(read_message(&mut reader), Err(BorthError::InvalidMessage));
+    }
+
+    #[test]
+    fn test3_write_framed_message() {
+        let mut output = vec![];
+        let message = BorthJson::object(vec![("id", BorthJson::Number(1))]);
+        assert_eq!(write_message(&mut output, &message), Ok(()));
+        assert_eq!(output, b"Content-Length: 8\r\n\r\n{\"id\":1}");
+    }
+}
@@ -1,21 +1,30 @@
-mod context;
-mod dict;
-mod errors;
-mod expression;
-mod interpreter;
-mod parser;
+mod command;
+mod debugger;
+mod failure;
+mod json;
+mod lsp;
+mod repl;
 mod runner;
+#[cfg(test)]
+mod shared_writer;
+mod source;
+mod tracer;
 
-use runner::BorthRunner;
+use runner::{BorthRunner, RUNNER_ERROR_STATUS};
+use std::process::ExitCode;
 
 /// Entrypoint for the Borth interpreter.
-fn main() {
+/// Exits with the status given to `bye-status`, 1 if the code failed or 2 if the runner did.
+fn main() -> ExitCode {
     let args: Vec<String> = std::env::args().collect();
-    let result = BorthRunner::from_args(&args)
-        .and_then(|runner| runner.start("stack.fth", &mut std::io::stdout()));
+    let result = BorthRunner::from_args(&args).and_then(|runner| runner.start(std::io::stdout()));
 
     // Handle error from runner
-    if let Err(error) = result {
-        print!("{}", error);
+    match result {
+        Ok(status) => ExitCode::from(status),
+        Err(error) => {
+            print!("{}", error);
+            ExitCode::from(RUNNER_ERROR_STATUS)
+        }
     }
 }
@@ -0,0 +1,80 @@
+use super::{context::BorthContext, errors::*};
+use std::{cell::RefCell, fmt::Debug, rc::Rc};
+
+type BorthCallback = Box<dyn FnMut(&mut BorthContext) -> BorthResult<()>>;
+
+/// Word implemented by the host application with a closure that may capture state
+pub struct BorthNative {
+    name: Rc<str>,
+    callback: RefCell<BorthCallback>,
+}
+
+impl BorthNative {
+    /// Create a new BorthNative instance
+    pub fn new(
+        name: &str,
+        callback: impl FnMut(&mut BorthContext) -> BorthResult<()> + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            callback: RefCell::new(Box::new(callback)),
+        }
+    }
+
+    /// Return the name the word was registered with
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Call the closure with its own call frame.
+    /// A closure that is already running can not be called again.
+    pub fn call(&self, ctx: &mut BorthContext) -> BorthResult<()> {
+        let mut callback = self
+            .callback
+            .try_borrow_mut()
+            .or(Err(BorthError::RuntimeError))?;
+        ctx.push_frame(&self.name);
+        callback(ctx)?;
+        ctx.pop_frame();
+        Ok(())
+    }
+}
+
+impl Debug for BorthNative {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("BorthNative").field(&self.name).finish()
+    }
+}
+
+/// Closures can not be compared, so a native word is only equal to itself
+impl PartialEq for BorthNative {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_call_with_captured_state() {
+        let mut ctx = BorthContext::with_stack_size(10);
+        let mut count = 0;
+        let native = BorthNative::new("next", move |ctx| {
+            count += 1;
+            ctx.push_value(count)
+        });
+        assert_eq!(native.call(&mut ctx), Ok(()));
+        assert_eq!(native.call(&mut ctx), Ok(()));
+        ctx.test(&[1, 2], "");
+    }
+
+    #[test]
+    fn test2_keep_frame_on_error() {
+        let mut ctx = BorthContext::with_stack_size(10);
+        let native = BorthNative::new("fail", |ctx| ctx.pop_value().map(|_| ()));
+        assert_eq!(native.call(&mut ctx), Err(BorthError::StackUnderflow));
+        assert_eq!(ctx.frames(), &["fail".into()]);
+    }
+}
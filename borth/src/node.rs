@@ -0,0 +1,63 @@
+use super::{
+    context::BorthContext, errors::*, expression::BorthExpression, position::BorthPosition,
+};
+use std::rc::Rc;
+
+#[derive(Debug)]
+/// An expression together with the position where it appears in the code
+pub struct BorthNode {
+    position: BorthPosition,
+    expression: Rc<BorthExpression>,
+}
+
+impl BorthNode {
+    /// Create a new BorthNode instance
+    pub fn new(position: BorthPosition, expression: Rc<BorthExpression>) -> Self {
+        Self {
+            position,
+            expression,
+        }
+    }
+
+    /// Return the position of the expression in the code
+    pub fn position(&self) -> BorthPosition {
+        self.position
+    }
+
+    /// Return the expression
+    pub fn expression(&self) -> &BorthExpression {
+        &self.expression
+    }
+
+    /// Return a shared reference to the expression
+    pub fn shared_expression(&self) -> Rc<BorthExpression> {
+        Rc::clone(&self.expression)
+    }
+
+    /// Take the shared reference to the expression, dropping the position
+    pub fn into_expression(self) -> Rc<BorthExpression> {
+        self.expression
+    }
+
+    /// Eval the expression and record its position if it fails
+    pub fn eval(&self, ctx: &mut BorthContext) -> BorthResult<()> {
+        self.expression.eval(ctx, self.position).inspect_err(|_| {
+            ctx.locate_error(self.position);
+        })
+    }
+
+    // testing
+
+    /// Wrap an expression without a known position
+    #[cfg(test)]
+    pub fn unlocated(expression: BorthExpression) -> Self {
+        Self::new(BorthPosition::default(), Rc::new(expression))
+    }
+}
+
+/// Nodes are compared by their expressions, positions are left aside
+impl PartialEq for BorthNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.expression == other.expression
+    }
+}
@@ -1,8 +1,7 @@
-use std::{rc::Rc, slice::Iter};
+use std::slice::Iter;
 
-use super::{dict::BorthDict, expression::BorthExpression};
+use super::{dict::BorthDict, node::BorthNode, position::BorthPosition, token::BorthToken};
 
-type BorthToken<'a> = (&'a str, &'a str);
 pub type BorthTokens<'a> = Vec<BorthToken<'a>>;
 pub type BorthIterator<'a> = Iter<'a, BorthToken<'a>>;
 
@@ -11,6 +10,7 @@ pub fn parse_tokens(code: &str) -> BorthTokens<'_> {
     let mut tokens = vec![];
     let mut whitespaces = code.match_indices(char::is_whitespace);
     let mut offset = 0;
+    let (mut line, mut column) = (1, 1);
     while offset < code.len() {
         let (stop, whitespace) = match whitespaces.next() {
             Some(result) => result,
@@ -20,19 +20,63 @@ pub fn parse_tokens(code: &str) -> BorthTokens<'_> {
             Some(token) => token,
             None => code,
         };
-        offset = stop + 1;
-        tokens.push((word, whitespace));
+        let position = BorthPosition::new(offset, line, column);
+        tokens.push(BorthToken::new(word, whitespace, position));
+        offset = stop + whitespace.len();
+        if whitespace == "\n" {
+            (line, column) = (line + 1, 1);
+        } else {
+            column += word.chars().count() + 1;
+        }
     }
     tokens
 }
 
 /// Parse tokens into expressions.
-pub fn parse_expressions(tokens: BorthTokens, dict: &mut BorthDict) -> Vec<Rc<BorthExpression>> {
+pub fn parse_expressions(tokens: BorthTokens, dict: &mut BorthDict) -> Vec<BorthNode> {
     let mut expressions = vec![];
     let mut iterator = tokens.iter();
     while let Some(expression) = dict.detect_next(&mut iterator) {
-        // println!("{:?}", expression);
         expressions.push(expression);
     }
     expressions
 }
+
+/// Skip the tokens up to the end of the given line
+pub fn skip_line(iterator: &mut BorthIterator, line: usize) {
+    while iterator
+        .as_slice()
+        .first()
+        .is_some_and(|token| token.position.line <= line)
+    {
+        iterator.next();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_position(token: &BorthToken, word: &str, line: usize, column: usize) {
+        assert_eq!(token.word, word);
+        assert_eq!((token.position.line, token.position.column), (line, column));
+    }
+
+    #[test]
+    fn test1_positions_in_one_line() {
+        let tokens = parse_tokens("1 22 +");
+        assert_position(&tokens[0], "1", 1, 1);
+        assert_position(&tokens[1], "22", 1, 3);
+        assert_position(&tokens[2], "+", 1, 6);
+        assert_eq!(tokens[2].position.offset, 5);
+    }
+
+    #[test]
+    fn test2_positions_in_many_lines() {
+        let tokens = parse_tokens("1 2\n  dup\n.");
+        assert_position(&tokens[1], "2", 1, 3);
+        assert_position(&tokens[4], "dup", 2, 3);
+        assert_position(&tokens[5], ".", 3, 1);
+        assert_eq!(tokens[5].position.offset, 10);
+    }
+}
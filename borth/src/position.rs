@@ -0,0 +1,43 @@
+use std::fmt::Display;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+/// Location of a token in the source code
+pub struct BorthPosition {
+    /// Byte offset from the start of the code
+    pub offset: usize,
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Column number in characters, starting at 1
+    pub column: usize,
+    /// Number of the named source the code came from, starting at 1, or 0 if the
+    /// code was not named
+    pub source: usize,
+}
+
+impl BorthPosition {
+    /// Create a new BorthPosition instance
+    pub fn new(offset: usize, line: usize, column: usize) -> Self {
+        Self {
+            offset,
+            line,
+            column,
+            source: 0,
+        }
+    }
+}
+
+impl Display for BorthPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_display_line_and_column() {
+        assert_eq!(BorthPosition::new(12, 3, 5).to_string(), "3:5");
+    }
+}
@@ -0,0 +1,6 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// What to do with the data stack when resuming after an error
+pub enum BorthRecovery {
+    ResetStack,
+    KeepStack,
+}
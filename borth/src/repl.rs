@@ -0,0 +1,120 @@
+use borth::{BorthError, BorthInterpreter, BorthResult, BorthStackView};
+use std::io::BufRead;
+
+/// Interactive session feeding lines to a long-lived interpreter
+pub struct BorthRepl<'a> {
+    interpreter: &'a mut BorthInterpreter,
+}
+
+impl<'a> BorthRepl<'a> {
+    /// Create a new BorthRepl instance over the given interpreter
+    pub fn new(interpreter: &'a mut BorthInterpreter) -> Self {
+        Self { interpreter }
+    }
+
+    /// Read lines until `bye` or the end of input, writing ` ok` to the
+    /// interpreter's output after each line that succeeds. A line calling
+    /// `bye` or `bye-status` also ends the session.
+    pub fn start(&mut self, reader: &mut impl BufRead) -> BorthResult<()> {
+        for line in reader.lines() {
+            let line = line.or(Err(BorthError::CanNotReadCode))?;
+            match line.trim().to_lowercase().as_str() {
+                "bye" => break,
+                ".s" => self.show_stack()?,
+                "words" => self.show_words()?,
+                _ => self.run_line(&line)?,
+            }
+            self.interpreter.flush_output()?;
+            if let Some(BorthError::Bye(_)) = self.interpreter.last_error() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn run_line(&mut self, line: &str) -> BorthResult<()> {
+        self.interpreter.run_line(line);
+        match self.interpreter.last_error() {
+            Some(BorthError::CanNotWriteToOutput) => Err(BorthError::CanNotWriteToOutput),
+            Some(_) => Ok(()),
+            None => self.interpreter.write_output(" ok\n"),
+        }
+    }
+
+    fn show_stack(&mut self) -> BorthResult<()> {
+        let stack = BorthStackView::new(self.interpreter.stack_items()).to_string();
+        self.interpreter.write_output(&format!("{} ok\n", stack))
+    }
+
+    fn show_words(&mut self) -> BorthResult<()> {
+        let words = self.interpreter.words().join(" ");
+        self.interpreter.write_output(&format!("{} ok\n", words))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borth::BorthRecovery;
+    use std::io::Cursor;
+
+    fn run_session(input: &str) -> (BorthInterpreter, String) {
+        let mut interpreter = BorthInterpreter::with_stack_size(20);
+        let mut reader = Cursor::new(input);
+        let result = BorthRepl::new(&mut interpreter).start(&mut reader);
+        assert_eq!(result, Ok(()));
+        let output = interpreter.output().to_string();
+        (interpreter, output)
+    }
+
+    #[test]
+    fn test1_ok_after_each_line() {
+        let (_, output) = run_session("1 2 + .\n3\n");
+        assert_eq!(output, "3 ok\n ok\n");
+    }
+
+    #[test]
+    fn test2_persistent_session() {
+        let (interpreter, output) = run_session(": sq dup * ;\n4 sq\n.s\n");
+        assert_eq!(output, " ok\n ok\n<1> 16 ok\n");
+        assert_eq!(interpreter.stack_items(), &[16]);
+    }
+
+    #[test]
+    fn test3_error_instead_of_ok() {
+        let (_, output) = run_session("1 foo\n+\n1 .\n");
+        assert_eq!(output, "?\nstack-underflow\n1 ok\n");
+    }
+
+    #[test]
+    fn test4_stop_at_bye() {
+        let (interpreter, output) = run_session("1\nBYE\n2\n");
+        assert_eq!(output, " ok\n");
+        assert_eq!(interpreter.stack_items(), &[1]);
+    }
+
+    #[test]
+    fn test5_list_words() {
+        let (_, output) = run_session("words\n");
+        assert!(output.starts_with("* + - . / < = > and bye bye-status cr drop dup"));
+        assert!(output.ends_with(" ok\n"));
+    }
+
+    #[test]
+    fn test6_stop_at_bye_word() {
+        let (interpreter, output) = run_session("1 2 3 bye-status\n4\n");
+        assert_eq!(output, "");
+        assert_eq!(interpreter.stack_items(), &[1, 2]);
+        assert_eq!(interpreter.last_error(), Some(&BorthError::Bye(3)));
+    }
+
+    #[test]
+    fn test7_keep_going_without_error_count() {
+        let mut interpreter = BorthInterpreter::with_stack_size(20);
+        interpreter.enable_keep_going(BorthRecovery::ResetStack);
+        let mut reader = Cursor::new("1 +\n2 .\n");
+        let result = BorthRepl::new(&mut interpreter).start(&mut reader);
+        assert_eq!(result, Ok(()));
+        assert_eq!(interpreter.output(), "stack-underflow\n2 ok\n");
+    }
+}
@@ -1,69 +1,575 @@
-use super::{context::BorthItem, errors::*, interpreter::*};
+use super::{
+    command::BorthCommand, debugger::BorthDebugger, failure::BorthFailure, json::BorthJson,
+    lsp::BorthLsp, repl::BorthRepl, source::BorthSource, tracer::BorthTracer,
+};
+use borth::{
+    BorthError, BorthFormatter, BorthHook, BorthInterpreter, BorthItem, BorthLimits, BorthRecovery,
+    BorthResult, BorthSandbox, BorthWriter,
+};
 use std::{
     fs::File,
-    io::{Read, Write},
+    io::{BufWriter, Write},
 };
 
-/// Store the path with the code to run and the stack size
+const REPL_SOURCE: &str = "<stdin>";
+
+/// Exit status of a run where the code failed with an error
+pub const RUNTIME_ERROR_STATUS: u8 = 1;
+
+/// Exit status of a run that failed before or after evaluating the code
+pub const RUNNER_ERROR_STATUS: u8 = 2;
+const DEFAULT_STACK_FILE: &str = "stack.fth";
+const VALUE_OPTIONS: [&str; 15] = [
+    "--eval",
+    "--output",
+    "--format",
+    "--stack-file",
+    "--load-stack",
+    "--image",
+    "--save-image",
+    "--keep-going",
+    "--deny",
+    "--stack-size",
+    "--max-steps",
+    "--max-output",
+    "--max-words",
+    "--max-code",
+    "--trace",
+];
+const USAGE: &str = "\
+Usage: borth [OPTIONS] [FILE | - | -e CODE]...
+       borth build [OPTIONS] (FILE | - | -e CODE) [-o PATH]
+       borth check [OPTIONS] [FILE | - | -e CODE]...
+       borth fmt [FILE | - | -e CODE]...
+       borth lsp
+       borth debug [OPTIONS] (FILE | -e CODE)
+
+Evaluates the files, the standard input (-) and the code given with -e in
+order, into the same interpreter. Without any of them, starts a session,
+or reads the standard input for the other commands.
+The build command translates one of them into a Rust program that runs it
+without the interpreter, to be built with rustc. The check command reports
+words with branches that leave different depths and code that would
+underflow the stack, without running anything. The fmt command rewrites
+the files with lowercase words, one definition per line and indented if
+bodies, and writes the formatted standard input or code to the output.
+The lsp command serves editors over the standard input and output, with
+the unknown words and unclosed definitions and conditionals of each file,
+the definition and stack effect of words on hover, where they were
+defined and the words of the dictionary as completions.
+The debug command evaluates one of them stopping before the first step,
+with commands from the standard input to step into or over words, set
+breakpoints on words and lines, and show the stacks and recent output.
+
+Options:
+  -e, --eval CODE        Evaluate CODE
+  -o, --output PATH      Write the built program to PATH instead of stdout
+  --repl                 Start a session after evaluating the sources
+  --format=FORMAT        Write the output as it is produced (text), or the
+                         output, stack, error and stats as one JSON document
+                         (json). JSON mode reads stdin instead of a session.
+  -O, --optimize         Optimize the words: fold constants, remove pairs like
+                         swap swap and inline tiny words
+  --strict               Check the ( a b -- c ) comments after the names of the
+                         words against what they do
+  --stack-size=BYTES     Size of the data stack
+  --stack-file=PATH      Save the final stack to PATH (default: stack.fth)
+  --no-stack-file        Do not save the final stack
+  --load-stack=PATH      Start with the stack saved in PATH
+  --resume               Start with the stack saved in stack.fth
+  --image=PATH           Start with the words saved in the image at PATH
+  --save-image=PATH      Save the words defined with : to an image at PATH
+  --max-steps=N          Stop after evaluating N expressions
+  --max-output=BYTES     Limit the output size
+  --max-words=N          Limit the number of definitions
+  --max-code=N           Limit the size of the definitions
+  --deny=GROUP,...       Deny output, input, file or system words
+  --diagnostics          Report errors with source, line, column and word
+  --backtrace            Print the called words and the stack after errors
+  --keep-going[=MODE]    Resume after errors, with reset or keep stack
+  --trace[=PATH]         Log each step with its depth and the stack after it
+                         to stderr, or to PATH
+  -h, --help             Print this help
+  -V, --version          Print the version
+
+Exits with the status given to bye-status, 1 if the code failed or 2 on other errors.
+";
+
+/// Store the sources of code to run, where to save the stack and the interpreter options
 pub struct BorthRunner {
-    code_path: String,
+    command: BorthCommand,
+    output: Option<String>,
+    sources: Vec<BorthSource>,
+    stack_file: Option<String>,
+    load_stack: Option<String>,
+    image: Option<String>,
+    save_image: Option<String>,
+    json: bool,
+    optimize: bool,
+    strict: bool,
+    repl: bool,
+    help: bool,
+    version: bool,
     stack_size: usize,
+    max_steps: Option<usize>,
+    limits: BorthLimits,
+    sandbox: BorthSandbox,
+    diagnostics: bool,
+    backtrace: bool,
+    recovery: Option<BorthRecovery>,
+    trace: bool,
+    trace_file: Option<String>,
 }
 
 impl BorthRunner {
-    /// Create a new BorthRunner instance with the given code path and stack size.
+    /// Create a new BorthRunner instance with the given sources and options.
     pub fn from_args(args: &[String]) -> BorthResult<Self> {
-        let (code_path, stack_size) = parse_args(args)?;
-        let stack_size = stack_size.unwrap_or(128_000);
-        Ok(Self {
-            code_path,
-            stack_size,
-        })
+        parse_args(args)
     }
 
-    /// Eval the code file, then save the stack to the given file and write output
-    pub fn start(&self, stack_file: &str, writer: &mut impl Write) -> BorthResult<()> {
-        let code = get_code_from_file(&self.code_path)?;
-        let mut interpreter = BorthInterpreter::with_stack_size(self.stack_size);
-        let (stack, output) = interpreter.run_code(&code);
-        let save_result = save_stack_to_file(stack, stack_file);
-        let write_result = write_output(writer, output);
-        save_result.and(write_result)
+    /// Eval the sources in order writing their output as it is produced, then start
+    /// a session if asked to. Finally, save the stack to the stack file, if any.
+    /// In JSON mode, write a single document with the results at the end instead.
+    /// Return the exit status: the one given to `bye-status`, or whether the code failed.
+    pub fn start(&self, mut writer: impl Write + 'static) -> BorthResult<u8> {
+        if self.help || self.version {
+            return write_to(&mut writer, &self.info()).and(Ok(0));
+        }
+        match self.command {
+            BorthCommand::Fmt => return self.format(writer).and(Ok(0)),
+            BorthCommand::Lsp => {
+                let mut reader = std::io::stdin().lock();
+                return BorthLsp::new().start(&mut reader, &mut writer).and(Ok(0));
+            }
+            _ => {}
+        }
+        let mut interpreter = self.create_interpreter()?;
+        match self.command {
+            BorthCommand::Debug => return self.debug(&mut interpreter, writer),
+            BorthCommand::Run | BorthCommand::Fmt | BorthCommand::Lsp => {}
+            BorthCommand::Build => return self.build(&mut interpreter, writer).and(Ok(0)),
+            BorthCommand::Check => return self.check(&mut interpreter, writer),
+        }
+        if !self.json {
+            interpreter.set_output(Box::new(BorthWriter::new(writer)));
+            return self.run(&mut interpreter).map(|(status, _)| status);
+        }
+        let (status, failure) = self.run(&mut interpreter)?;
+        let report = json_report(&mut interpreter, status, failure.as_ref()).to_string();
+        write_to(&mut writer, &format!("{}\n", report)).and(Ok(status))
     }
-}
 
-fn parse_args(args: &[String]) -> BorthResult<(String, Option<usize>)> {
-    let len = args.len();
-    if len < 2 {
-        return Err(BorthError::MissingArguments);
+    /// Run the sources and then the REPL, and return the exit status with the error
+    /// of the last source that failed
+    fn run(&self, interpreter: &mut BorthInterpreter) -> BorthResult<(u8, Option<BorthFailure>)> {
+        let session_result = self.run_sources(interpreter).and_then(|(status, failure)| {
+            if !self.repl || self.json || bye_status(interpreter).is_some() {
+                return Ok((status, failure));
+            }
+            interpreter.set_source(REPL_SOURCE);
+            BorthRepl::new(interpreter).start(&mut std::io::stdin().lock())?;
+            Ok((bye_status(interpreter).unwrap_or(status), failure))
+        });
+        let flush_result = interpreter.flush_output();
+        let save_result = match &self.stack_file {
+            Some(path) => save_stack_to_file(interpreter.stack_items(), path),
+            None => Ok(()),
+        };
+        let image_result = match &self.save_image {
+            Some(path) => save_image_to_file(interpreter, path),
+            None => Ok(()),
+        };
+        save_result
+            .and(image_result)
+            .and(session_result)
+            .and_then(|session| flush_result.and(Ok(session)))
+    }
+
+    /// Translate the only source into a Rust program that saves the stack like the run
+    /// would, and write it to the output path or the writer
+    fn build(&self, interpreter: &mut BorthInterpreter, mut writer: impl Write) -> BorthResult<()> {
+        let source = match self.sources.as_slice() {
+            [source] => source,
+            _ => return Err(BorthError::TooManyArguments),
+        };
+        let program = interpreter.transpile(&source.read()?, self.stack_file.as_deref())?;
+        match &self.output {
+            Some(path) => std::fs::write(path, program).or(Err(BorthError::CanNotWriteFile)),
+            None => write_to(&mut writer, &program),
+        }
     }
-    if len > 3 {
-        return Err(BorthError::TooManyArguments);
+
+    /// Run the only source with a debugger that reads commands from stdin and writes
+    /// to the writer, keeping the output of the code for the debugger to show
+    fn debug(
+        &self,
+        interpreter: &mut BorthInterpreter,
+        writer: impl Write + 'static,
+    ) -> BorthResult<u8> {
+        let source = match self.sources.as_slice() {
+            [] => return Err(BorthError::MissingValue("debug".to_string())),
+            [source] => source,
+            _ => return Err(BorthError::TooManyArguments),
+        };
+        let code = source.read()?;
+        let reader = std::io::stdin().lock();
+        let debugger = BorthDebugger::new(reader, writer, source.name(), &code);
+        interpreter.set_hook(Some(Box::new(debugger)));
+        self.run(interpreter).map(|(status, _)| status)
     }
-    let path = args[1].to_string();
-    if args.len() <= 2 {
-        return Ok((path, None));
+
+    /// Report the issues found in the sources, one per line, and return whether there
+    /// were any as the exit status
+    fn check(&self, interpreter: &mut BorthInterpreter, mut writer: impl Write) -> BorthResult<u8> {
+        let mut status = 0;
+        for source in &self.sources {
+            for issue in interpreter.check(&source.read()?) {
+                write_to(&mut writer, &format!("{}\n", issue.describe(source.name())))?;
+                status = RUNTIME_ERROR_STATUS;
+            }
+        }
+        Ok(status)
     }
-    if !args[2].contains("--stack-size=") {
-        return Err(BorthError::BadArguments);
+
+    /// Format the files in place, and the other sources to the writer
+    fn format(&self, mut writer: impl Write) -> BorthResult<()> {
+        for source in &self.sources {
+            let code = source.read()?;
+            let formatted = BorthFormatter::format(&code);
+            match source {
+                BorthSource::File(path) if formatted != code => {
+                    std::fs::write(path, formatted).or(Err(BorthError::CanNotWriteFile))?
+                }
+                BorthSource::File(_) => {}
+                _ => write_to(&mut writer, &formatted)?,
+            }
+        }
+        Ok(())
     }
-    match args[2].replace("--stack-size=", "").parse::<usize>() {
-        Ok(value) => Ok((path, Some(value))),
-        _ => Err(BorthError::BadArguments),
+
+    fn info(&self) -> String {
+        match self.help {
+            true => USAGE.to_string(),
+            false => format!("borth {}\n", env!("CARGO_PKG_VERSION")),
+        }
     }
-}
 
-fn get_code_from_file(path: &str) -> BorthResult<String> {
-    match File::open(path) {
-        Ok(mut file) => {
-            let mut code = Default::default();
-            if file.read_to_string(&mut code).is_err() {
-                return Err(BorthError::CanNotReadCode);
+    fn create_interpreter(&self) -> BorthResult<BorthInterpreter> {
+        let mut interpreter = BorthInterpreter::with_sandbox(self.stack_size, self.sandbox);
+        interpreter.set_max_steps(self.max_steps);
+        interpreter.set_limits(self.limits);
+        if self.optimize {
+            interpreter.enable_optimizer();
+        }
+        if self.strict {
+            interpreter.enable_strict();
+        }
+        if self.diagnostics {
+            interpreter.enable_diagnostics();
+        }
+        if self.backtrace {
+            interpreter.enable_backtrace();
+        }
+        if self.json {
+            interpreter.silence_errors();
+        }
+        if let Some(recovery) = self.recovery {
+            interpreter.enable_keep_going(recovery);
+        }
+        if self.trace {
+            self.enable_trace(&mut interpreter)?;
+        }
+        if let Some(path) = &self.image {
+            load_image_from_file(&mut interpreter, path)?;
+        }
+        if let Some(path) = &self.load_stack {
+            load_stack_from_file(&mut interpreter, path)?;
+        }
+        Ok(interpreter)
+    }
+
+    /// Log the steps to the trace file, or to stderr so the output stays as it is
+    fn enable_trace(&self, interpreter: &mut BorthInterpreter) -> BorthResult<()> {
+        let tracer: Box<dyn BorthHook> = match &self.trace_file {
+            Some(path) => {
+                let file = File::create(path).or(Err(BorthError::CanNotWriteFile))?;
+                Box::new(BorthTracer::new(BufWriter::new(file)))
+            }
+            None => Box::new(BorthTracer::new(std::io::stderr())),
+        };
+        interpreter.set_hook(Some(tracer));
+        Ok(())
+    }
+
+    /// Run the sources until one calls `bye`, and return the exit status with the
+    /// error of the last source that failed
+    fn run_sources(
+        &self,
+        interpreter: &mut BorthInterpreter,
+    ) -> BorthResult<(u8, Option<BorthFailure>)> {
+        let mut status = 0;
+        let mut failure = None;
+        for source in &self.sources {
+            let code = source.read()?;
+            interpreter.set_source(source.name());
+            interpreter.run_code(&code);
+            match interpreter.last_error() {
+                Some(BorthError::CanNotWriteToOutput) => {
+                    return Err(BorthError::CanNotWriteToOutput);
+                }
+                Some(BorthError::Bye(bye_status)) => {
+                    return Ok((exit_status(*bye_status), failure));
+                }
+                Some(_) => {
+                    status = RUNTIME_ERROR_STATUS;
+                    failure = BorthFailure::from_last_error(interpreter);
+                }
+                None => {}
+            }
+        }
+        Ok((status, failure))
+    }
+
+    fn parse_arg<'a>(
+        &mut self,
+        arg: &'a str,
+        rest: &mut impl Iterator<Item = &'a String>,
+    ) -> BorthResult<()> {
+        match arg {
+            "-e" | "--eval" => {
+                let code = rest
+                    .next()
+                    .ok_or(BorthError::MissingValue(arg.to_string()))?;
+                self.sources.push(BorthSource::Eval(code.to_string()));
+            }
+            "-o" => {
+                let path = rest
+                    .next()
+                    .ok_or(BorthError::MissingValue(arg.to_string()))?;
+                self.output = Some(path.to_string());
+            }
+            "-" => self.add_stdin()?,
+            "-h" | "--help" => self.help = true,
+            "-V" | "--version" => self.version = true,
+            "-O" | "--optimize" => self.optimize = true,
+            "--strict" => self.strict = true,
+            "--repl" => self.repl = true,
+            "--no-stack-file" => self.stack_file = None,
+            "--resume" => self.load_stack = Some(DEFAULT_STACK_FILE.to_string()),
+            "--diagnostics" => self.diagnostics = true,
+            "--backtrace" => self.backtrace = true,
+            "--keep-going" => self.recovery = Some(BorthRecovery::ResetStack),
+            "--trace" => self.trace = true,
+            _ if arg.starts_with('-') => return self.parse_value(arg),
+            _ => self.sources.push(BorthSource::File(arg.to_string())),
+        }
+        Ok(())
+    }
+
+    fn add_stdin(&mut self) -> BorthResult<()> {
+        if self.sources.contains(&BorthSource::Stdin) {
+            return Err(BorthError::TooManyArguments);
+        }
+        self.sources.push(BorthSource::Stdin);
+        Ok(())
+    }
+
+    fn parse_value(&mut self, arg: &str) -> BorthResult<()> {
+        let (option, value) = arg.split_once('=').unwrap_or((arg, ""));
+        if !VALUE_OPTIONS.contains(&option) {
+            return Err(BorthError::UnknownOption(option.to_string()));
+        }
+        if value.is_empty() {
+            return Err(BorthError::MissingValue(option.to_string()));
+        }
+        match option {
+            "--eval" => self.sources.push(BorthSource::Eval(value.to_string())),
+            "--output" => self.output = Some(value.to_string()),
+            "--format" => self.json = parse_format(arg, value)?,
+            "--stack-file" => self.stack_file = Some(value.to_string()),
+            "--load-stack" => self.load_stack = Some(value.to_string()),
+            "--image" => self.image = Some(value.to_string()),
+            "--save-image" => self.save_image = Some(value.to_string()),
+            "--keep-going" => self.recovery = Some(parse_recovery(arg, value)?),
+            "--deny" => self.parse_denied(arg, value)?,
+            "--stack-size" => self.stack_size = parse_number(arg, value)?,
+            "--max-steps" => self.max_steps = Some(parse_number(arg, value)?),
+            "--max-output" => self.limits.max_output = Some(parse_number(arg, value)?),
+            "--max-words" => self.limits.max_words = Some(parse_number(arg, value)?),
+            "--max-code" => self.limits.max_code = Some(parse_number(arg, value)?),
+            "--trace" => {
+                self.trace = true;
+                self.trace_file = Some(value.to_string());
             }
-            Ok(code)
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn parse_denied(&mut self, arg: &str, groups: &str) -> BorthResult<()> {
+        for group in groups.split(',') {
+            match group {
+                "output" => self.sandbox.output = true,
+                "input" => self.sandbox.input = true,
+                "file" => self.sandbox.file = true,
+                "system" => self.sandbox.system = true,
+                _ => return Err(BorthError::InvalidValue(arg.to_string())),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Return the exit status, if the last run called `bye` or `bye-status`
+fn bye_status(interpreter: &BorthInterpreter) -> Option<u8> {
+    match interpreter.last_error() {
+        Some(BorthError::Bye(status)) => Some(exit_status(*status)),
+        _ => None,
+    }
+}
+
+/// Keep the low byte of the value, as the system does with exit statuses
+fn exit_status(value: BorthItem) -> u8 {
+    (value & 0xff) as u8
+}
+
+fn parse_number(arg: &str, value: &str) -> BorthResult<usize> {
+    value
+        .parse()
+        .or(Err(BorthError::InvalidValue(arg.to_string())))
+}
+
+fn parse_format(arg: &str, value: &str) -> BorthResult<bool> {
+    match value {
+        "text" => Ok(false),
+        "json" => Ok(true),
+        _ => Err(BorthError::InvalidValue(arg.to_string())),
+    }
+}
+
+fn parse_recovery(arg: &str, value: &str) -> BorthResult<BorthRecovery> {
+    match value {
+        "reset" => Ok(BorthRecovery::ResetStack),
+        "keep" => Ok(BorthRecovery::KeepStack),
+        _ => Err(BorthError::InvalidValue(arg.to_string())),
+    }
+}
+
+fn parse_args(args: &[String]) -> BorthResult<BorthRunner> {
+    let mut runner = BorthRunner {
+        command: BorthCommand::Run,
+        output: None,
+        sources: vec![],
+        stack_file: Some(DEFAULT_STACK_FILE.to_string()),
+        load_stack: None,
+        image: None,
+        save_image: None,
+        json: false,
+        optimize: false,
+        strict: false,
+        repl: false,
+        help: false,
+        version: false,
+        stack_size: BorthInterpreter::DEFAULT_STACK_SIZE,
+        max_steps: None,
+        limits: BorthLimits::default(),
+        sandbox: BorthSandbox::default(),
+        diagnostics: false,
+        backtrace: false,
+        recovery: None,
+        trace: false,
+        trace_file: None,
+    };
+    let mut rest = args.iter().skip(1).peekable();
+    if let Some(command) = rest.peek().and_then(|arg| BorthCommand::from_name(arg)) {
+        runner.command = command;
+        rest.next();
+    }
+    while let Some(arg) = rest.next() {
+        runner.parse_arg(arg, &mut rest)?;
+    }
+    let reads_stdin = runner.json || runner.command != BorthCommand::Run;
+    let has_own_input = matches!(runner.command, BorthCommand::Lsp | BorthCommand::Debug);
+    if runner.sources.is_empty() && !has_own_input {
+        match reads_stdin {
+            true => runner.sources.push(BorthSource::Stdin),
+            false => runner.repl = true,
         }
-        _ => Err(BorthError::CanNotReadFile),
     }
+    Ok(runner)
+}
+
+fn write_to(writer: &mut impl Write, text: &str) -> BorthResult<()> {
+    writer
+        .write_all(text.as_bytes())
+        .and_then(|_| writer.flush())
+        .or(Err(BorthError::CanNotWriteToOutput))
+}
+
+/// Describe the output, stack, error and stats left by the run, with its exit status
+fn json_report(
+    interpreter: &mut BorthInterpreter,
+    status: u8,
+    failure: Option<&BorthFailure>,
+) -> BorthJson {
+    let stack = interpreter
+        .stack_items()
+        .iter()
+        .map(|item| BorthJson::Number((*item).into()))
+        .collect();
+    let stats = interpreter.stats();
+    BorthJson::object(vec![
+        ("output", BorthJson::String(interpreter.take_output())),
+        ("stack", BorthJson::Array(stack)),
+        (
+            "error",
+            BorthJson::from_option(failure, BorthFailure::to_json),
+        ),
+        ("exit_status", BorthJson::Number(status.into())),
+        (
+            "stats",
+            BorthJson::object(vec![
+                ("steps", BorthJson::Number(stats.steps as i128)),
+                (
+                    "max_stack_depth",
+                    BorthJson::Number(stats.max_stack_depth as i128),
+                ),
+                (
+                    "output_bytes",
+                    BorthJson::Number(stats.output_bytes as i128),
+                ),
+            ]),
+        ),
+    ])
+}
+
+/// Push the items saved in the file, the oldest first. Fail if an item is not a number,
+/// does not fit in a cell or does not fit in the stack.
+fn load_stack_from_file(interpreter: &mut BorthInterpreter, path: &str) -> BorthResult<()> {
+    let text = BorthSource::File(path.to_string()).read()?;
+    for item in parse_stack(&text)? {
+        interpreter.push(item)?;
+    }
+    Ok(())
+}
+
+fn parse_stack(text: &str) -> BorthResult<Vec<BorthItem>> {
+    text.split_whitespace()
+        .map(|word| {
+            let value = word.parse::<i128>().or(Err(BorthError::InvalidStackFile))?;
+            BorthItem::try_from(value).or(Err(BorthError::StackItemOutOfRange))
+        })
+        .collect()
+}
+
+/// Restore the words saved in the image file
+fn load_image_from_file(interpreter: &mut BorthInterpreter, path: &str) -> BorthResult<()> {
+    let image = std::fs::read(path).or(Err(BorthError::CanNotReadFile))?;
+    interpreter.load_image(&image)
+}
+
+fn save_image_to_file(interpreter: &BorthInterpreter, path: &str) -> BorthResult<()> {
+    std::fs::write(path, interpreter.save_image()).or(Err(BorthError::CanNotWriteFile))
 }
 
 fn save_stack_to_file(stack: &[BorthItem], path_to_file: &str) -> BorthResult<()> {
@@ -85,20 +591,17 @@ fn save_stack_to_file(stack: &[BorthItem], path_to_file: &str) -> BorthResult<()
     }
 }
 
-fn write_output(writer: &mut impl Write, output: &str) -> BorthResult<()> {
-    write!(writer, "{}", output).or(Err(BorthError::CanNotWriteToOutput))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::shared_writer::SharedWriter;
     use std::io::Cursor;
 
     fn create_args() -> Vec<String> {
         vec!["forth".into()]
     }
 
-    fn create_runner(args: &Vec<String>) -> BorthResult<BorthRunner> {
+    fn create_runner(args: &[String]) -> BorthResult<BorthRunner> {
         BorthRunner::from_args(args)
     }
 
@@ -106,17 +609,25 @@ mod tests {
         Vec::new()
     }
 
-    fn run(runner: BorthRunner) -> BorthResult<()> {
-        runner.start("/tmp/borth-test-stack.fth", &mut create_writer())
+    fn with_stack_file(mut runner: BorthRunner, path: &str) -> BorthRunner {
+        runner.stack_file = Some(path.to_string());
+        runner
+    }
+
+    fn run(runner: BorthRunner) -> BorthResult<u8> {
+        with_stack_file(runner, "/tmp/borth-test-stack.fth").start(create_writer())
     }
 
     #[test]
-    fn test1_expect_filename_in_args() {
+    fn test1_repl_without_filename() {
         let mut args = create_args();
-        assert!(parse_args(&args).is_err());
+        assert!(parse_args(&args).is_ok_and(|runner| runner.repl));
 
         args.push("ruta/a/main.fth".into());
-        assert!(parse_args(&args).is_ok());
+        assert!(parse_args(&args).is_ok_and(|runner| !runner.repl));
+
+        args.push("--repl".into());
+        assert!(parse_args(&args).is_ok_and(|runner| runner.repl));
     }
 
     #[test]
@@ -125,7 +636,7 @@ mod tests {
         args.push("ruta/a/main.fth".into());
         args.push("--stack-size=10".into());
 
-        assert!(parse_args(&args).is_ok_and(|(_, size)| size == Some(10)));
+        assert!(parse_args(&args).is_ok_and(|runner| runner.stack_size == 10));
     }
 
     #[test]
@@ -133,7 +644,10 @@ mod tests {
         let mut args = create_args();
         args.push("ruta/a/main.fth".into());
 
-        assert!(parse_args(&args).is_ok_and(|(_, size)| size.is_none()));
+        assert!(
+            parse_args(&args)
+                .is_ok_and(|runner| runner.stack_size == BorthInterpreter::DEFAULT_STACK_SIZE)
+        );
     }
 
     #[test]
@@ -153,7 +667,7 @@ mod tests {
         let runner = create_runner(&args);
 
         assert!(runner.is_ok());
-        assert_eq!(runner.and_then(|r| run(r)), Err(BorthError::CanNotReadFile));
+        assert_eq!(runner.and_then(run), Err(BorthError::CanNotReadFile));
     }
 
     #[test]
@@ -164,7 +678,9 @@ mod tests {
 
         assert!(runner.is_ok());
         assert_eq!(
-            runner.and_then(|r| r.start("/tmp/borth/test-stack.fth", &mut create_writer())),
+            runner.and_then(
+                |r| with_stack_file(r, "/tmp/borth/test-stack.fth").start(create_writer())
+            ),
             Err(BorthError::CanNotWriteFile)
         );
     }
@@ -174,12 +690,480 @@ mod tests {
         let mut args = create_args();
         args.push("./fth-examples/3.fth".into());
         let runner = create_runner(&args);
-        let mut writer = Cursor::new([]);
+        let writer = Cursor::new([]);
 
         assert!(runner.is_ok());
         assert_eq!(
-            runner.and_then(|r| r.start("/tmp/borth-test-stack.fth", &mut writer)),
+            runner.and_then(|r| with_stack_file(r, "/tmp/borth-test-stack.fth").start(writer)),
             Err(BorthError::CanNotWriteToOutput)
         );
     }
+
+    #[test]
+    fn test8_parse_sources_in_order() {
+        let mut args = create_args();
+        args.push("ruta/a/main.fth".into());
+        args.push("-e".into());
+        args.push("1 2 +".into());
+        args.push("-".into());
+        args.push("--eval=3".into());
+        let expected = vec![
+            BorthSource::File("ruta/a/main.fth".into()),
+            BorthSource::Eval("1 2 +".into()),
+            BorthSource::Stdin,
+            BorthSource::Eval("3".into()),
+        ];
+        assert!(parse_args(&args).is_ok_and(|runner| runner.sources == expected && !runner.repl));
+    }
+
+    #[test]
+    fn test9_reject_stdin_twice() {
+        let mut args = create_args();
+        args.push("-".into());
+        args.push("-".into());
+        assert!(matches!(
+            parse_args(&args),
+            Err(BorthError::TooManyArguments)
+        ));
+    }
+
+    #[test]
+    fn test10_eval_needs_code() {
+        let mut args = create_args();
+        args.push("-e".into());
+        assert_eq!(
+            parse_args(&args).err(),
+            Some(BorthError::MissingValue("-e".into()))
+        );
+    }
+
+    #[test]
+    fn test11_parse_stack_file() {
+        let mut args = create_args();
+        assert!(
+            parse_args(&args).is_ok_and(|runner| runner.stack_file.as_deref() == Some("stack.fth"))
+        );
+
+        args.push("--stack-file=otro.fth".into());
+        assert!(
+            parse_args(&args).is_ok_and(|runner| runner.stack_file.as_deref() == Some("otro.fth"))
+        );
+
+        args.push("--no-stack-file".into());
+        assert!(parse_args(&args).is_ok_and(|runner| runner.stack_file.is_none()));
+
+        args[1] = "--stack-file=".into();
+        assert_eq!(
+            parse_args(&args).err(),
+            Some(BorthError::MissingValue("--stack-file".into()))
+        );
+    }
+
+    #[test]
+    fn test12_parse_max_steps() {
+        let mut args = create_args();
+        args.push("ruta/a/main.fth".into());
+        assert!(parse_args(&args).is_ok_and(|runner| runner.max_steps.is_none()));
+
+        args.push("--max-steps=1000".into());
+        assert!(parse_args(&args).is_ok_and(|runner| runner.max_steps == Some(1000)));
+
+        args[2] = "--max-steps=many".into();
+        assert_eq!(
+            parse_args(&args).err(),
+            Some(BorthError::InvalidValue("--max-steps=many".into()))
+        );
+    }
+
+    #[test]
+    fn test13_parse_limits() {
+        let mut args = create_args();
+        args.push("ruta/a/main.fth".into());
+        args.push("--max-output=10".into());
+        args.push("--max-words=20".into());
+        args.push("--max-code=30".into());
+        let expected = BorthLimits {
+            max_output: Some(10),
+            max_words: Some(20),
+            max_code: Some(30),
+        };
+        assert!(parse_args(&args).is_ok_and(|runner| runner.limits == expected));
+    }
+
+    #[test]
+    fn test14_parse_diagnostics_in_any_order() {
+        let mut args = create_args();
+        args.push("ruta/a/main.fth".into());
+        args.push("--diagnostics".into());
+        args.push("--stack-size=10".into());
+
+        assert!(
+            parse_args(&args).is_ok_and(|runner| runner.diagnostics && runner.stack_size == 10)
+        );
+    }
+
+    #[test]
+    fn test15_reject_unknown_option() {
+        let mut args = create_args();
+        args.push("ruta/a/main.fth".into());
+        args.push("--verbose".into());
+        assert_eq!(
+            parse_args(&args).err(),
+            Some(BorthError::UnknownOption("--verbose".into()))
+        );
+
+        args[2] = "--stack-size".into();
+        assert_eq!(
+            parse_args(&args).err(),
+            Some(BorthError::MissingValue("--stack-size".into()))
+        );
+    }
+
+    #[test]
+    fn test16_parse_backtrace() {
+        let mut args = create_args();
+        args.push("ruta/a/main.fth".into());
+        args.push("--backtrace".into());
+
+        assert!(parse_args(&args).is_ok_and(|runner| runner.backtrace && !runner.diagnostics));
+    }
+
+    #[test]
+    fn test17_parse_keep_going() {
+        let mut args = create_args();
+        args.push("ruta/a/main.fth".into());
+        args.push("--keep-going".into());
+        assert!(
+            parse_args(&args)
+                .is_ok_and(|runner| runner.recovery == Some(BorthRecovery::ResetStack))
+        );
+
+        args[2] = "--keep-going=keep".into();
+        assert!(
+            parse_args(&args).is_ok_and(|runner| runner.recovery == Some(BorthRecovery::KeepStack))
+        );
+    }
+
+    #[test]
+    fn test18_parse_deny() {
+        let mut args = create_args();
+        args.push("ruta/a/main.fth".into());
+        args.push("--deny=output,file".into());
+        let expected = BorthSandbox {
+            output: true,
+            file: true,
+            ..Default::default()
+        };
+        assert!(parse_args(&args).is_ok_and(|runner| runner.sandbox == expected));
+
+        args[2] = "--deny=network".into();
+        assert_eq!(
+            parse_args(&args).err(),
+            Some(BorthError::InvalidValue("--deny=network".into()))
+        );
+    }
+
+    #[test]
+    fn test19_runner_evaluates_sources_in_one_interpreter() {
+        let mut args = create_args();
+        args.push("./fth-examples/3.fth".into());
+        args.push("-e".into());
+        args.push(": borth-test 7 ; borth-test".into());
+        args.push("--no-stack-file".into());
+        let runner = create_runner(&args);
+        assert!(runner.is_ok_and(|r| r.start(create_writer()) == Ok(0)));
+    }
+
+    #[test]
+    fn test20_runtime_error_status() {
+        let mut args = create_args();
+        args.push("-e".into());
+        args.push("1 +".into());
+        args.push("-e".into());
+        args.push("2".into());
+        args.push("--no-stack-file".into());
+        let runner = create_runner(&args);
+        assert!(runner.is_ok_and(|r| r.start(create_writer()) == Ok(RUNTIME_ERROR_STATUS)));
+    }
+
+    #[test]
+    fn test21_bye_status_stops_the_sources() {
+        let mut args = create_args();
+        args.push("-e".into());
+        args.push("-1 bye-status".into());
+        args.push("./fth-examples/0.fth".into());
+        args.push("--no-stack-file".into());
+        let runner = create_runner(&args);
+        assert!(runner.is_ok_and(|r| r.start(create_writer()) == Ok(255)));
+
+        let path = "/tmp/borth-test-bye-stack.fth";
+        args[2] = "3 4 + bye".into();
+        args[4] = format!("--stack-file={}", path);
+        let runner = create_runner(&args);
+        assert!(runner.is_ok_and(|r| r.start(create_writer()) == Ok(0)));
+        assert_eq!(std::fs::read_to_string(path).ok(), Some("7".to_string()));
+    }
+
+    #[test]
+    fn test22_help_and_version_run_nothing() {
+        let mut args = create_args();
+        args.push("./fth-examples/0.fth".into());
+        args.push("--help".into());
+        assert!(create_runner(&args).is_ok_and(|r| r.start(create_writer()).is_ok()));
+
+        args[2] = "--version".into();
+        assert!(create_runner(&args).is_ok_and(|r| r.start(create_writer()).is_ok()));
+    }
+
+    #[test]
+    fn test23_parse_resume() {
+        let mut args = create_args();
+        assert!(parse_args(&args).is_ok_and(|runner| runner.load_stack.is_none()));
+
+        args.push("--resume".into());
+        assert!(parse_args(&args).is_ok_and(|r| r.load_stack.as_deref() == Some("stack.fth")));
+
+        args.push("--load-stack=otro.fth".into());
+        assert!(parse_args(&args).is_ok_and(|r| r.load_stack.as_deref() == Some("otro.fth")));
+    }
+
+    #[test]
+    fn test24_parse_stack() {
+        assert_eq!(parse_stack(" 1 -2\n3 "), Ok(vec![1, -2, 3]));
+        assert_eq!(parse_stack(""), Ok(vec![]));
+        assert_eq!(parse_stack("1 dos"), Err(BorthError::InvalidStackFile));
+        assert_eq!(parse_stack("1 40000"), Err(BorthError::StackItemOutOfRange));
+    }
+
+    #[test]
+    fn test25_resume_from_saved_stack() {
+        let path = "/tmp/borth-test-resume.fth";
+        let mut args = create_args();
+        args.push("-e".into());
+        args.push("1 2".into());
+        args.push(format!("--stack-file={}", path));
+        assert!(create_runner(&args).is_ok_and(|r| r.start(create_writer()) == Ok(0)));
+
+        args[2] = "+".into();
+        args.push(format!("--load-stack={}", path));
+        assert!(create_runner(&args).is_ok_and(|r| r.start(create_writer()) == Ok(0)));
+        assert!(std::fs::read_to_string(path).is_ok_and(|stack| stack == "3"));
+    }
+
+    #[test]
+    fn test26_resume_beyond_capacity() {
+        let path = "/tmp/borth-test-resume-full.fth";
+        assert!(std::fs::write(path, "1 2 3").is_ok());
+        let mut args = create_args();
+        args.push("--stack-size=4".into());
+        args.push(format!("--load-stack={}", path));
+        args.push(format!("--stack-file={}", path));
+        let runner = create_runner(&args);
+        assert_eq!(
+            runner.and_then(|r| r.start(create_writer())),
+            Err(BorthError::StackOverflow)
+        );
+        assert!(std::fs::read_to_string(path).is_ok_and(|stack| stack == "1 2 3"));
+    }
+
+    #[test]
+    fn test27_json_report() {
+        let mut args = create_args();
+        args.push("--format=json".into());
+        args.push("-e".into());
+        args.push(": foo 1 + ;\n1 2 . drop foo".into());
+        args.push("--no-stack-file".into());
+        let (writer, written) = SharedWriter::new();
+        let runner = create_runner(&args);
+        assert!(runner.is_ok_and(|r| r.start(writer) == Ok(RUNTIME_ERROR_STATUS)));
+        let expected = concat!(
+            r#"{"output":"2","stack":[],"#,
+            r#""error":{"name":"stack-underflow","source":"<eval>","line":1,"column":9,"word":"foo"},"#,
+            r#""exit_status":1,"stats":{"steps":8,"max_stack_depth":2,"output_bytes":1}}"#,
+            "\n"
+        );
+        assert_eq!(String::from_utf8_lossy(&written.borrow()), expected);
+    }
+
+    #[test]
+    fn test28_parse_format() {
+        let mut args = create_args();
+        args.push("--format=json".into());
+        assert!(
+            parse_args(&args)
+                .is_ok_and(|r| r.json && !r.repl && r.sources == vec![BorthSource::Stdin])
+        );
+
+        args[1] = "--format=xml".into();
+        assert_eq!(
+            parse_args(&args).err(),
+            Some(BorthError::InvalidValue("--format=xml".into()))
+        );
+    }
+
+    #[test]
+    fn test29_restore_words_from_saved_image() {
+        let path = "/tmp/borth-test-image.img";
+        let mut args = create_args();
+        args.push("-e".into());
+        args.push(": foo 1 ; : bar foo 2 ; : foo 3 ;".into());
+        args.push(format!("--save-image={}", path));
+        assert!(create_runner(&args).is_ok_and(|r| run(r) == Ok(0)));
+
+        let (writer, written) = SharedWriter::new();
+        args[2] = "bar foo . . .".into();
+        args[3] = format!("--image={}", path);
+        let runner = create_runner(&args).map(|r| with_stack_file(r, "/tmp/borth-test-stack.fth"));
+        assert!(runner.is_ok_and(|r| r.start(writer) == Ok(0)));
+        assert_eq!(String::from_utf8_lossy(&written.borrow()), "3 2 1");
+    }
+
+    #[test]
+    fn test30_load_missing_image() {
+        let mut args = create_args();
+        args.push("--image=/tmp/borth-test-missing.img".into());
+        let runner = create_runner(&args);
+        assert_eq!(runner.and_then(run), Err(BorthError::CanNotReadFile));
+    }
+
+    #[test]
+    fn test31_parse_build() {
+        let mut args = create_args();
+        args.push("build".into());
+        args.push("prog.fth".into());
+        args.push("-o".into());
+        args.push("prog.rs".into());
+        assert!(
+            parse_args(&args).is_ok_and(|r| r.command == BorthCommand::Build
+                && r.output.as_deref() == Some("prog.rs")
+                && r.sources == vec![BorthSource::File("prog.fth".into())])
+        );
+
+        args[1] = "build.fth".into();
+        assert!(parse_args(&args).is_ok_and(|r| r.command == BorthCommand::Run));
+    }
+
+    #[test]
+    fn test32_build_program() {
+        let path = "/tmp/borth-test-build.rs";
+        let mut args = create_args();
+        args.push("build".into());
+        args.push("-e".into());
+        args.push(": foo 1 + ; 2 foo .".into());
+        args.push(format!("--output={}", path));
+        assert!(create_runner(&args).is_ok_and(|r| r.start(create_writer()) == Ok(0)));
+        assert!(std::fs::read_to_string(path).is_ok_and(|program| {
+            program.contains("const STACK_FILE: Option<&str> = Some(\"stack.fth\");")
+                && program.contains("fn word_0(")
+        }));
+
+        args.push("-e".into());
+        args.push("1".into());
+        assert_eq!(
+            create_runner(&args).and_then(run),
+            Err(BorthError::TooManyArguments)
+        );
+    }
+
+    #[test]
+    fn test33_check_sources() {
+        let (writer, written) = SharedWriter::new();
+        let mut args = create_args();
+        args.push("check".into());
+        args.push("-e".into());
+        args.push(": foo if 1 then ;".into());
+        args.push("--eval=1 +".into());
+        let runner = create_runner(&args);
+        assert!(runner.is_ok_and(|r| r.start(writer) == Ok(RUNTIME_ERROR_STATUS)));
+        let expected = concat!(
+            "<eval>:1:7: inconsistent-stack-effect in word foo\n",
+            "<eval>:1:3: stack-underflow\n"
+        );
+        assert_eq!(String::from_utf8_lossy(&written.borrow()), expected);
+    }
+
+    #[test]
+    fn test34_format_files_in_place() {
+        let path = "/tmp/borth-test-format.fth";
+        assert!(std::fs::write(path, ": SQ DUP * ;\n3 SQ .").is_ok());
+        let (writer, written) = SharedWriter::new();
+        let mut args = create_args();
+        args.push("fmt".into());
+        args.push(path.into());
+        args.push("-e".into());
+        args.push("1 2 +   .".into());
+        let runner = create_runner(&args);
+        assert!(runner.is_ok_and(|r| r.start(writer) == Ok(0)));
+        assert!(std::fs::read_to_string(path).is_ok_and(|code| code == ": sq dup * ;\n3 sq .\n"));
+        assert_eq!(String::from_utf8_lossy(&written.borrow()), "1 2 + .\n");
+    }
+
+    #[test]
+    fn test35_lsp_without_sources_or_session() {
+        let mut args = create_args();
+        args.push("lsp".into());
+        let runner = create_runner(&args);
+        assert!(
+            runner.is_ok_and(|r| {
+                r.command == BorthCommand::Lsp && r.sources.is_empty() && !r.repl
+            })
+        );
+    }
+
+    #[test]
+    fn test36_debug_one_source() {
+        let mut args = create_args();
+        args.push("debug".into());
+        let runner = create_runner(&args);
+        assert!(
+            runner
+                .as_ref()
+                .is_ok_and(|r| r.sources.is_empty() && !r.repl)
+        );
+        let result = runner.and_then(run);
+        assert_eq!(result, Err(BorthError::MissingValue("debug".into())));
+        args.push("-e".into());
+        args.push("1".into());
+        args.push("a.fth".into());
+        assert_eq!(
+            create_runner(&args).and_then(run),
+            Err(BorthError::TooManyArguments)
+        );
+    }
+
+    #[test]
+    fn test37_trace_to_file() {
+        let path = "/tmp/borth-test-trace.log";
+        let mut args = create_args();
+        args.push("--trace".into());
+        args.push("-e".into());
+        args.push(": sq dup * ; 3 sq".into());
+        assert!(
+            create_runner(&args)
+                .as_ref()
+                .is_ok_and(|r| r.trace && r.trace_file.is_none())
+        );
+        args[1] = format!("--trace={}", path);
+        assert_eq!(create_runner(&args).and_then(run), Ok(0));
+        let trace = std::fs::read_to_string(path).unwrap_or_default();
+        assert!(trace.ends_with("sq <1> 3\n  dup <2> 3 3\n  * <1> 9\n"));
+    }
+
+    #[test]
+    fn test38_json_report_keeps_the_failing_source_error() {
+        let mut args = create_args();
+        args.push("--format=json".into());
+        args.extend(["-e", "1 +", "-e", "2"].map(String::from));
+        args.push("--no-stack-file".into());
+        let (writer, written) = SharedWriter::new();
+        let runner = create_runner(&args);
+        assert!(runner.is_ok_and(|r| r.start(writer) == Ok(RUNTIME_ERROR_STATUS)));
+        let expected = concat!(
+            r#"{"output":"","stack":[2],"#,
+            r#""error":{"name":"stack-underflow","source":"<eval>","line":1,"column":3,"word":null},"#,
+            r#""exit_status":1,"stats":{"steps":3,"max_stack_depth":1,"output_bytes":0}}"#,
+            "\n"
+        );
+        assert_eq!(String::from_utf8_lossy(&written.borrow()), expected);
+    }
 }
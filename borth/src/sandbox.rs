@@ -0,0 +1,64 @@
+const OUTPUT_WORDS: [&str; 4] = [".", "emit", "cr", ".\""];
+const INPUT_WORDS: [&str; 0] = [];
+const FILE_WORDS: [&str; 0] = [];
+const SYSTEM_WORDS: [&str; 2] = ["bye", "bye-status"];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+/// Groups of builtin words denied to the program. A denied word fails
+/// with `ForbiddenWord` unless the program defines it again.
+pub struct BorthSandbox {
+    /// Words that write to the output
+    pub output: bool,
+    /// Words that read input
+    pub input: bool,
+    /// Words that touch files
+    pub file: bool,
+    /// Words that act on the running process
+    pub system: bool,
+}
+
+impl BorthSandbox {
+    /// Return the builtin words of the denied groups
+    pub fn denied_words(&self) -> Vec<&'static str> {
+        [
+            (self.output, &OUTPUT_WORDS[..]),
+            (self.input, &INPUT_WORDS[..]),
+            (self.file, &FILE_WORDS[..]),
+            (self.system, &SYSTEM_WORDS[..]),
+        ]
+        .into_iter()
+        .filter(|(denied, _)| *denied)
+        .flat_map(|(_, words)| words.iter().copied())
+        .collect()
+    }
+
+    /// Return true if the given builtin word belongs to a denied group
+    pub fn denies(&self, word: &str) -> bool {
+        self.denied_words()
+            .iter()
+            .any(|denied| denied.eq_ignore_ascii_case(word))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_nothing_denied_by_default() {
+        let sandbox = BorthSandbox::default();
+        assert!(sandbox.denied_words().is_empty());
+        assert!(!sandbox.denies("emit"));
+    }
+
+    #[test]
+    fn test2_deny_output() {
+        let sandbox = BorthSandbox {
+            output: true,
+            ..Default::default()
+        };
+        assert!(sandbox.denies("EMIT"));
+        assert!(sandbox.denies(".\""));
+        assert!(!sandbox.denies("dup"));
+    }
+}
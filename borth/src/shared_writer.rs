@@ -0,0 +1,23 @@
+use std::{cell::RefCell, io::Write, rc::Rc};
+
+/// Writer appending to a buffer the test keeps, to read what was written after
+/// the writer was handed over
+pub struct SharedWriter(Rc<RefCell<Vec<u8>>>);
+
+impl SharedWriter {
+    /// Create a new SharedWriter instance and the buffer it writes to
+    pub fn new() -> (Self, Rc<RefCell<Vec<u8>>>) {
+        let written = Rc::new(RefCell::new(Vec::new()));
+        (Self(Rc::clone(&written)), written)
+    }
+}
+
+impl Write for SharedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,25 @@
+pub mod buffer;
+pub mod writer;
+
+use super::errors::BorthResult;
+
+/// Destination of the output produced by an execution
+pub trait BorthSink {
+    /// Write a string to the sink
+    fn write_str(&mut self, str: &str) -> BorthResult<()>;
+
+    /// Flush any output not yet delivered
+    fn flush(&mut self) -> BorthResult<()> {
+        Ok(())
+    }
+
+    /// Take the output kept in memory, leaving it empty
+    fn take(&mut self) -> String {
+        String::new()
+    }
+
+    /// Return the output kept in memory, if the sink keeps any
+    fn contents(&self) -> &str {
+        ""
+    }
+}
@@ -0,0 +1,37 @@
+use crate::{errors::BorthResult, sink::BorthSink};
+
+#[derive(Default)]
+/// Sink keeping the whole output in memory
+pub struct BorthBuffer {
+    output: String,
+}
+
+impl BorthSink for BorthBuffer {
+    fn write_str(&mut self, str: &str) -> BorthResult<()> {
+        self.output.push_str(str);
+        Ok(())
+    }
+
+    fn take(&mut self) -> String {
+        self.output.split_off(0)
+    }
+
+    fn contents(&self) -> &str {
+        &self.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_keep_output() {
+        let mut buffer = BorthBuffer::default();
+        assert_eq!(buffer.write_str("hello"), Ok(()));
+        assert_eq!(buffer.write_str(" world"), Ok(()));
+        assert_eq!(buffer.contents(), "hello world");
+        assert_eq!(buffer.take(), "hello world");
+        assert_eq!(buffer.contents(), "");
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{errors::*, sink::BorthSink};
+use std::io::Write;
+
+/// Sink writing the output through to a writer as soon as it is produced
+pub struct BorthWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> BorthWriter<W> {
+    /// Create a new BorthWriter instance over the given writer
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: Write> BorthSink for BorthWriter<W> {
+    fn write_str(&mut self, str: &str) -> BorthResult<()> {
+        self.writer
+            .write_all(str.as_bytes())
+            .or(Err(BorthError::CanNotWriteToOutput))
+    }
+
+    fn flush(&mut self) -> BorthResult<()> {
+        self.writer.flush().or(Err(BorthError::CanNotWriteToOutput))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test1_write_through() {
+        let mut sink = BorthWriter::new(Cursor::new(Vec::new()));
+        assert_eq!(sink.write_str("hello"), Ok(()));
+        assert_eq!(sink.writer.get_ref(), b"hello");
+        assert_eq!(sink.contents(), "");
+    }
+
+    #[test]
+    fn test2_can_not_write() {
+        let mut sink = BorthWriter::new(Cursor::new([0u8; 2]));
+        assert_eq!(
+            sink.write_str("hello"),
+            Err(BorthError::CanNotWriteToOutput)
+        );
+    }
+}
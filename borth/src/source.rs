@@ -0,0 +1,63 @@
+use borth::{BorthError, BorthResult};
+use std::{fs::File, io::Read};
+
+/// Where a piece of code to run comes from
+#[derive(Debug, PartialEq)]
+pub enum BorthSource {
+    File(String),
+    Stdin,
+    Eval(String),
+}
+
+impl BorthSource {
+    /// Return the name used for the source in diagnostics
+    pub fn name(&self) -> &str {
+        match self {
+            BorthSource::File(path) => path,
+            BorthSource::Stdin => "<stdin>",
+            BorthSource::Eval(_) => "<eval>",
+        }
+    }
+
+    /// Read the code from the source
+    pub fn read(&self) -> BorthResult<String> {
+        match self {
+            BorthSource::File(path) => get_code_from_file(path),
+            BorthSource::Stdin => read_code(&mut std::io::stdin()),
+            BorthSource::Eval(code) => Ok(code.to_string()),
+        }
+    }
+}
+
+fn get_code_from_file(path: &str) -> BorthResult<String> {
+    match File::open(path) {
+        Ok(mut file) => read_code(&mut file),
+        _ => Err(BorthError::CanNotReadFile),
+    }
+}
+
+fn read_code(reader: &mut impl Read) -> BorthResult<String> {
+    let mut code = String::new();
+    match reader.read_to_string(&mut code) {
+        Ok(_) => Ok(code),
+        Err(_) => Err(BorthError::CanNotReadCode),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_read_eval() {
+        let source = BorthSource::Eval("1 2 +".into());
+        assert_eq!(source.read(), Ok("1 2 +".to_string()));
+        assert_eq!(source.name(), "<eval>");
+    }
+
+    #[test]
+    fn test2_read_missing_file() {
+        let source = BorthSource::File("./fth-examples/0.fth".into());
+        assert_eq!(source.read(), Err(BorthError::CanNotReadFile));
+    }
+}
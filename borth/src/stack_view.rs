@@ -0,0 +1,35 @@
+use super::context::BorthItem;
+use std::fmt::Display;
+
+/// Display of the data stack as Forth's `.s` does: the depth followed by the items
+pub struct BorthStackView<'a> {
+    items: &'a [BorthItem],
+}
+
+impl<'a> BorthStackView<'a> {
+    /// Create a new BorthStackView instance
+    pub fn new(items: &'a [BorthItem]) -> Self {
+        Self { items }
+    }
+}
+
+impl Display for BorthStackView<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{}>", self.items.len())?;
+        for item in self.items {
+            write!(f, " {}", item)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1_display_depth_and_items() {
+        assert_eq!(BorthStackView::new(&[1, -2, 3]).to_string(), "<3> 1 -2 3");
+        assert_eq!(BorthStackView::new(&[]).to_string(), "<0>");
+    }
+}
@@ -0,0 +1,10 @@
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+/// Counters of what the evaluation did since the interpreter was created or reset
+pub struct BorthStats {
+    /// Expressions evaluated
+    pub steps: usize,
+    /// Largest number of items the stack held
+    pub max_stack_depth: usize,
+    /// Bytes of output produced by the program
+    pub output_bytes: usize,
+}
@@ -0,0 +1,120 @@
+pub mod action;
+
+use super::{
+    dict::builtin_name, expression::BorthExpression, position::BorthPosition,
+    vm::instruction::BorthInstruction,
+};
+use action::BorthAction;
+
+#[derive(Debug, Clone, Copy)]
+/// A step of the evaluation, shown to the hook before it is taken
+pub struct BorthStep<'a> {
+    position: BorthPosition,
+    action: BorthAction<'a>,
+}
+
+impl<'a> BorthStep<'a> {
+    /// Create the step that evaluates an expression
+    pub(crate) fn expression(position: BorthPosition, expression: &'a BorthExpression) -> Self {
+        let action = BorthAction::Expression(expression);
+        Self { position, action }
+    }
+
+    /// Create the step that runs an instruction, or `None` for the instructions
+    /// that take no step of their own
+    pub(crate) fn instruction(
+        position: BorthPosition,
+        instruction: &'a BorthInstruction,
+    ) -> Option<Self> {
+        match instruction {
+            BorthInstruction::Eval(_) | BorthInstruction::Jump(_) | BorthInstruction::Return => {
+                None
+            }
+            _ => Some(Self {
+                position,
+                action: BorthAction::Instruction(instruction),
+            }),
+        }
+    }
+
+    /// Return where the step is in the code
+    pub fn position(&self) -> BorthPosition {
+        self.position
+    }
+
+    /// Return the name of the word the step calls, if it calls one
+    pub fn word(&self) -> Option<&'a str> {
+        match self.action {
+            BorthAction::Expression(BorthExpression::Word(name, ..))
+            | BorthAction::Instruction(BorthInstruction::Call(name, _)) => Some(name),
+            BorthAction::Expression(BorthExpression::Native(native)) => Some(native.name()),
+            _ => None,
+        }
+    }
+
+    /// Describe what the step does with the word it comes from in the code
+    pub fn describe(&self) -> String {
+        match self.action {
+            BorthAction::Expression(expression) => describe_expression(expression),
+            BorthAction::Instruction(BorthInstruction::Push(value)) => value.to_string(),
+            BorthAction::Instruction(BorthInstruction::Operation(operation)) => {
+                builtin_name(*operation).unwrap_or_default().to_string()
+            }
+            BorthAction::Instruction(BorthInstruction::BranchIfZero(_)) => "if".to_string(),
+            BorthAction::Instruction(_) => self.word().unwrap_or_default().to_string(),
+        }
+    }
+}
+
+fn describe_expression(expression: &BorthExpression) -> String {
+    match expression {
+        BorthExpression::Number(value) => value.to_string(),
+        BorthExpression::Operation(operation) => {
+            builtin_name(*operation).unwrap_or_default().to_string()
+        }
+        BorthExpression::Native(native) => native.name().to_string(),
+        BorthExpression::DotQuote(str) => format!(".\" {}\"", str),
+        BorthExpression::IfElseThen(..) => "if".to_string(),
+        BorthExpression::Word(name, ..) => name.to_string(),
+        BorthExpression::UnknownWord(word) => word.to_string(),
+        BorthExpression::WordCreated => ":".to_string(),
+        expression => expression
+            .parse_error()
+            .map(|error| error.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::arithmetic::add;
+    use std::rc::Rc;
+
+    #[test]
+    fn test1_describe_expressions_and_instructions() {
+        let position = BorthPosition::default();
+        let number = BorthExpression::Number(-3);
+        assert_eq!(BorthStep::expression(position, &number).describe(), "-3");
+        let quote = BorthExpression::DotQuote("hi".into());
+        assert_eq!(
+            BorthStep::expression(position, &quote).describe(),
+            ".\" hi\""
+        );
+        let add = BorthInstruction::Operation(add::call);
+        let step = BorthStep::instruction(position, &add);
+        assert_eq!(step.map(|step| step.describe()), Some("+".to_string()));
+        assert!(BorthStep::instruction(position, &BorthInstruction::Return).is_none());
+    }
+
+    #[test]
+    fn test2_word_called_by_the_step() {
+        let position = BorthPosition::new(4, 1, 5);
+        let call = BorthInstruction::Call("foo".into(), Rc::default());
+        let step = BorthStep::instruction(position, &call);
+        assert_eq!(step.and_then(|step| step.word()), Some("foo"));
+        assert_eq!(step.map(|step| step.position()), Some(position));
+        let number = BorthExpression::Number(1);
+        assert_eq!(BorthStep::expression(position, &number).word(), None);
+    }
+}
@@ -0,0 +1,8 @@
+use crate::{expression::BorthExpression, vm::instruction::BorthInstruction};
+
+/// What a step does: evaluate an expression, or run an instruction of compiled code
+#[derive(Debug, Clone, Copy)]
+pub enum BorthAction<'a> {
+    Expression(&'a BorthExpression),
+    Instruction(&'a BorthInstruction),
+}
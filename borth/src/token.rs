@@ -0,0 +1,20 @@
+use super::position::BorthPosition;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A word from the source code, the whitespace that follows it and its position
+pub struct BorthToken<'a> {
+    pub word: &'a str,
+    pub whitespace: &'a str,
+    pub position: BorthPosition,
+}
+
+impl<'a> BorthToken<'a> {
+    /// Create a new BorthToken instance
+    pub fn new(word: &'a str, whitespace: &'a str, position: BorthPosition) -> Self {
+        Self {
+            word,
+            whitespace,
+            position,
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use borth::{BorthContext, BorthError, BorthHook, BorthResult, BorthStackView, BorthStep};
+use std::io::Write;
+
+/// Log of every step of the evaluation, one per line, indented by the number of
+/// words being called and followed by the stack the step leaves
+pub struct BorthTracer<W> {
+    writer: W,
+    pending: Option<(usize, String)>,
+}
+
+impl<W: Write> BorthTracer<W> {
+    /// Create a new BorthTracer instance writing to the writer
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            pending: None,
+        }
+    }
+
+    /// Write the step taken last with the stack it left, now that it is known
+    fn write_pending(&mut self, ctx: &BorthContext) -> BorthResult<()> {
+        let Some((depth, word)) = self.pending.take() else {
+            return Ok(());
+        };
+        let stack = BorthStackView::new(ctx.stack_items());
+        let indent = "  ".repeat(depth);
+        writeln!(self.writer, "{}{} {}", indent, word, stack)
+            .or(Err(BorthError::CanNotWriteToOutput))
+    }
+}
+
+impl<W: Write> BorthHook for BorthTracer<W> {
+    /// Log the previous step, since this one starts where it ended
+    fn before_step(&mut self, ctx: &BorthContext, step: &BorthStep) -> BorthResult<()> {
+        self.write_pending(ctx)?;
+        self.pending = Some((ctx.frames().len(), step.describe()));
+        Ok(())
+    }
+
+    /// Log the last step of the run
+    fn after_run(&mut self, ctx: &BorthContext) -> BorthResult<()> {
+        self.write_pending(ctx)?;
+        self.writer.flush().or(Err(BorthError::CanNotWriteToOutput))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared_writer::SharedWriter;
+    use borth::BorthInterpreter;
+
+    fn trace(code: &str) -> String {
+        let (writer, written) = SharedWriter::new();
+        let tracer = BorthTracer::new(writer);
+        let mut interpreter = BorthInterpreter::new();
+        interpreter.set_hook(Some(Box::new(tracer)));
+        interpreter.run_code(code);
+        String::from_utf8_lossy(&written.borrow()).to_string()
+    }
+
+    #[test]
+    fn test1_trace_steps_with_depth_and_stack() {
+        let expected = concat!(
+            ": <0>\n",
+            "3 <1> 3\n",
+            "sq <1> 3\n",
+            "  dup <2> 3 3\n",
+            "  * <1> 9\n",
+            "if <0>\n",
+            ".\" big\" <0>\n",
+        );
+        assert_eq!(trace(": sq dup * ;\n3 sq if .\" big\" then"), expected);
+    }
+
+    #[test]
+    fn test2_trace_failing_step() {
+        assert_eq!(trace("1 +"), "1 <1> 1\n+ <0>\n");
+    }
+}
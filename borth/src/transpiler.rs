@@ -0,0 +1,233 @@
+//! Translation of Borth code into a standalone Rust program.
+//!
+//! Each word defined with `:` becomes a function and the code outside definitions
+//! becomes the `program` function. They run on a small runtime, kept in
+//! `transpiler/runtime.rs`, with the 16-bit cells, output spacing, error messages,
+//! exit statuses and stack file of `BorthRunner`.
+
+use super::{
+    callgraph::{self, BorthWordIds, word_id},
+    dict::builtin_name,
+    errors::*,
+    expression::BorthExpression,
+    node::BorthNode,
+};
+
+const RUNTIME: &str = include_str!("transpiler/runtime.rs");
+const INDENT: &str = "    ";
+
+/// Return a Rust program that runs the nodes, with a stack of `capacity` items
+/// saved to `stack_file` at the end. Words from the host can not be translated.
+pub fn transpile(
+    nodes: &[BorthNode],
+    capacity: usize,
+    stack_file: Option<&str>,
+) -> BorthResult<String> {
+    let mut roots = vec![];
+    callgraph::push_callees(nodes, &mut roots);
+    let words = callgraph::sorted_words(roots.into_iter());
+    let ids = callgraph::word_ids(&words);
+    let mut program = header(capacity, stack_file);
+    program.push_str(RUNTIME);
+    for (id, word) in words.iter().enumerate() {
+        if let BorthExpression::Word(name, body, _) = word.as_ref() {
+            let comment = format!("// : {}", name);
+            write_function(&mut program, &comment, &format!("word_{}", id), body, &ids)?;
+        }
+    }
+    write_function(&mut program, "// code", "program", nodes, &ids)?;
+    Ok(program)
+}
+
+/// Write the constants the runtime takes from the interpreter
+fn header(capacity: usize, stack_file: Option<&str>) -> String {
+    let mut header = String::from("//! Generated by `borth build`.\n#![allow(dead_code)]\n\n");
+    header.push_str(&format!("const STACK_CAPACITY: usize = {};\n", capacity));
+    header.push_str(&format!(
+        "const STACK_FILE: Option<&str> = {:?};\n",
+        stack_file
+    ));
+    for error in [
+        BorthError::StackUnderflow,
+        BorthError::StackOverflow,
+        BorthError::DivisionByZero,
+        BorthError::RuntimeError,
+        BorthError::CanNotWriteFile,
+        BorthError::CanNotWriteToOutput,
+    ] {
+        let message = error.to_string();
+        let constant = message.replace('-', "_").to_uppercase();
+        header.push_str(&format!("const {}: &str = {:?};\n", constant, message));
+    }
+    header.push('\n');
+    header
+}
+
+fn write_function(
+    program: &mut String,
+    comment: &str,
+    name: &str,
+    body: &[BorthNode],
+    ids: &BorthWordIds,
+) -> BorthResult<()> {
+    program.push_str(&format!(
+        "\n{}\nfn {}(machine: &mut Machine) -> Step {{\n",
+        comment, name
+    ));
+    write_nodes(program, body, ids, 1)?;
+    program.push_str(INDENT);
+    program.push_str("Ok(())\n}\n");
+    Ok(())
+}
+
+fn write_nodes(
+    program: &mut String,
+    nodes: &[BorthNode],
+    ids: &BorthWordIds,
+    depth: usize,
+) -> BorthResult<()> {
+    let indent = INDENT.repeat(depth);
+    for node in nodes {
+        if let BorthExpression::IfElseThen(if_block, else_block) = node.expression() {
+            program.push_str(&format!("{}if machine.pop()? != 0 {{\n", indent));
+            write_nodes(program, if_block, ids, depth + 1)?;
+            program.push_str(&format!("{}}} else {{\n", indent));
+            write_nodes(program, else_block, ids, depth + 1)?;
+            program.push_str(&format!("{}}}\n", indent));
+        } else if let Some(statement) = statement(node.expression(), ids)? {
+            program.push_str(&format!("{}{}\n", indent, statement));
+        }
+    }
+    Ok(())
+}
+
+/// Return the statement that runs an expression other than `if`, if it does anything
+fn statement(expression: &BorthExpression, ids: &BorthWordIds) -> BorthResult<Option<String>> {
+    let error = match expression {
+        BorthExpression::Number(value) => return Ok(Some(format!("machine.push({})?;", value))),
+        BorthExpression::Operation(operation) => {
+            let method = method(builtin_name(*operation).unwrap_or_default());
+            return Ok(Some(format!("machine.{}()?;", method)));
+        }
+        BorthExpression::DotQuote(str) => return Ok(Some(format!("machine.print({:?})?;", str))),
+        BorthExpression::Word(..) => {
+            return Ok(Some(format!(
+                "word_{}(machine)?;",
+                word_id(ids, expression)
+            )));
+        }
+        BorthExpression::Native(native) => {
+            return Err(BorthError::UnknownWord(native.name().to_string()));
+        }
+        BorthExpression::UnknownWord(word) => BorthError::UnknownWord(word.to_string()),
+        BorthExpression::IncompleteStatement => BorthError::IncompleteStatement,
+        BorthExpression::InvalidWord => BorthError::InvalidWord,
+        BorthExpression::ForbiddenWord => BorthError::ForbiddenWord,
+        BorthExpression::WordLimitExceeded => BorthError::WordLimitExceeded,
+        BorthExpression::CodeLimitExceeded => BorthError::CodeLimitExceeded,
+        BorthExpression::StackEffectMismatch(word) => {
+            BorthError::StackEffectMismatch(word.to_string())
+        }
+        BorthExpression::IfElseThen(..) | BorthExpression::WordCreated => return Ok(None),
+    };
+    Ok(Some(format!("fail({:?})?;", error.to_string())))
+}
+
+/// Return the runtime method of a builtin word
+fn method(builtin: &str) -> &str {
+    match builtin {
+        "+" => "add",
+        "-" => "sub",
+        "*" => "mul",
+        "/" => "div",
+        "=" => "eq",
+        "<" => "lt",
+        ">" => "gt",
+        "." => "dot",
+        "bye-status" => "bye_status",
+        _ => builtin,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::BorthInterpreter;
+    use std::process::Command;
+
+    fn create_interpreter() -> BorthInterpreter {
+        BorthInterpreter::with_stack_size(20)
+    }
+
+    /// Build the program with rustc and run it in a new directory,
+    /// returning its exit status, output and saved stack
+    fn build_and_run(name: &str, program: &str) -> Option<(i32, String, String)> {
+        let dir = std::env::temp_dir().join(format!("borth-build-{}", name));
+        std::fs::create_dir_all(&dir).ok()?;
+        std::fs::write(dir.join("prog.rs"), program).ok()?;
+        let built = Command::new("rustc")
+            .args(["-D", "warnings", "-o", "prog", "prog.rs"])
+            .current_dir(&dir)
+            .status()
+            .ok()?;
+        assert!(built.success());
+        let run = Command::new(dir.join("prog"))
+            .current_dir(&dir)
+            .output()
+            .ok()?;
+        let stack = std::fs::read_to_string(dir.join("stack.fth")).ok()?;
+        let output = String::from_utf8_lossy(&run.stdout).to_string();
+        Some((run.status.code()?, output, stack))
+    }
+
+    #[test]
+    fn test1_translate_words_to_functions() {
+        let program = create_interpreter().transpile(": foo 1 + ; 2 foo .", None);
+        assert!(program.is_ok_and(|program| {
+            program.contains("// : foo\nfn word_0(machine: &mut Machine) -> Step {\n    machine.push(1)?;\n    machine.add()?;\n    Ok(())\n}\n")
+                && program.contains("word_0(machine)?;\n    machine.dot()?;\n")
+                && program.contains("const STACK_FILE: Option<&str> = None;")
+        }));
+    }
+
+    #[test]
+    fn test2_fail_with_words_from_the_host() {
+        let mut interpreter = create_interpreter();
+        assert_eq!(interpreter.register_word("host", |_| Ok(())), Ok(()));
+        assert_eq!(
+            interpreter.transpile("1 host", None),
+            Err(BorthError::UnknownWord("host".to_string()))
+        );
+    }
+
+    #[test]
+    fn test3_built_program_runs_like_the_interpreter() {
+        let code = ": foo 1 + ; : bar foo foo ; : foo 10 ;
+            0 bar . 65 emit .\" hi\" cr
+            -7 2 / 3 4 over rot swap 1 if 7 else 8 then bar foo drop drop 9 . nope 5";
+        let mut interpreter = create_interpreter();
+        let program = interpreter.transpile(code, Some("stack.fth"));
+        let built = program
+            .ok()
+            .and_then(|program| build_and_run("run", &program));
+
+        let mut interpreter = create_interpreter();
+        interpreter.run_code(code);
+        let stack: Vec<String> = interpreter
+            .stack_items()
+            .iter()
+            .map(|i| i.to_string())
+            .collect();
+        let expected = (1, interpreter.output().to_string(), stack.join(" "));
+        assert_eq!(built, Some(expected));
+    }
+
+    #[test]
+    fn test4_built_program_exits_with_bye_status() {
+        let program = create_interpreter().transpile("1 2 3 bye-status 4", Some("stack.fth"));
+        let built = program
+            .ok()
+            .and_then(|program| build_and_run("bye", &program));
+        assert_eq!(built, Some((3, String::new(), "1 2".to_string())));
+    }
+}
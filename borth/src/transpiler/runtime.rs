@@ -0,0 +1,210 @@
+use std::io::{BufWriter, Stdout, Write};
+use std::process::ExitCode;
+
+/// Why the program stopped before its end
+enum Stop {
+    Error(&'static str),
+    Bye(i16),
+    Output,
+}
+
+type Step = Result<(), Stop>;
+
+/// Exit status of a run that failed before or after evaluating the code
+const RUNNER_ERROR_STATUS: u8 = 2;
+
+fn fail(message: &'static str) -> Step {
+    Err(Stop::Error(message))
+}
+
+/// Data stack and output, with the same rules as the interpreter
+struct Machine {
+    stack: Vec<i16>,
+    output: BufWriter<Stdout>,
+    last_char: Option<char>,
+}
+
+impl Machine {
+    fn new() -> Self {
+        Self {
+            stack: Vec::with_capacity(STACK_CAPACITY),
+            output: BufWriter::new(std::io::stdout()),
+            last_char: None,
+        }
+    }
+
+    fn pop(&mut self) -> Result<i16, Stop> {
+        self.stack.pop().ok_or(Stop::Error(STACK_UNDERFLOW))
+    }
+
+    fn push(&mut self, value: i16) -> Step {
+        if self.stack.len() == STACK_CAPACITY {
+            return Err(Stop::Error(STACK_OVERFLOW));
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn binary(&mut self, operation: fn(i16, i16) -> i16) -> Step {
+        let value1 = self.pop()?;
+        let value2 = self.pop()?;
+        self.push(operation(value2, value1))
+    }
+
+    fn flag(value: bool) -> i16 {
+        if value { -1 } else { 0 }
+    }
+
+    fn add(&mut self) -> Step {
+        self.binary(i16::wrapping_add)
+    }
+
+    fn sub(&mut self) -> Step {
+        self.binary(i16::wrapping_sub)
+    }
+
+    fn mul(&mut self) -> Step {
+        self.binary(i16::wrapping_mul)
+    }
+
+    fn div(&mut self) -> Step {
+        let value1 = self.pop()?;
+        let value2 = self.pop()?;
+        if value1 == 0 {
+            return Err(Stop::Error(DIVISION_BY_ZERO));
+        }
+        self.push(value2.wrapping_div(value1))
+    }
+
+    fn dup(&mut self) -> Step {
+        let value1 = self.pop()?;
+        self.push(value1)?;
+        self.push(value1)
+    }
+
+    fn drop(&mut self) -> Step {
+        self.pop().map(|_| ())
+    }
+
+    fn swap(&mut self) -> Step {
+        let value1 = self.pop()?;
+        let value2 = self.pop()?;
+        self.push(value1)?;
+        self.push(value2)
+    }
+
+    fn over(&mut self) -> Step {
+        let value1 = self.pop()?;
+        let value2 = self.pop()?;
+        self.push(value2)?;
+        self.push(value1)?;
+        self.push(value2)
+    }
+
+    fn rot(&mut self) -> Step {
+        let value1 = self.pop()?;
+        let value2 = self.pop()?;
+        let value3 = self.pop()?;
+        self.push(value2)?;
+        self.push(value1)?;
+        self.push(value3)
+    }
+
+    fn eq(&mut self) -> Step {
+        self.binary(|value2, value1| Self::flag(value2 == value1))
+    }
+
+    fn lt(&mut self) -> Step {
+        self.binary(|value2, value1| Self::flag(value2 < value1))
+    }
+
+    fn gt(&mut self) -> Step {
+        self.binary(|value2, value1| Self::flag(value2 > value1))
+    }
+
+    fn and(&mut self) -> Step {
+        self.binary(|value2, value1| value2 & value1)
+    }
+
+    fn or(&mut self) -> Step {
+        self.binary(|value2, value1| value2 | value1)
+    }
+
+    fn not(&mut self) -> Step {
+        let value1 = self.pop()?;
+        self.push(Self::flag(value1 == 0))
+    }
+
+    fn dot(&mut self) -> Step {
+        let value1 = self.pop()?;
+        self.print(&value1.to_string())
+    }
+
+    fn emit(&mut self) -> Step {
+        let value1 = self.pop()?;
+        let ascii = char::from_u32(value1 as u32).ok_or(Stop::Error(RUNTIME_ERROR))?;
+        self.print(&ascii.to_string())
+    }
+
+    fn cr(&mut self) -> Step {
+        self.write("\n")
+    }
+
+    fn bye(&mut self) -> Step {
+        Err(Stop::Bye(0))
+    }
+
+    fn bye_status(&mut self) -> Step {
+        let status = self.pop()?;
+        Err(Stop::Bye(status))
+    }
+
+    /// Write a string separated by a space from the previous output
+    fn print(&mut self, str: &str) -> Step {
+        if self.last_char.is_some_and(|c| !c.is_whitespace()) {
+            self.write(" ")?;
+        }
+        self.write(str)
+    }
+
+    fn write(&mut self, str: &str) -> Step {
+        self.output.write_all(str.as_bytes()).or(Err(Stop::Output))?;
+        if let Some(last) = str.chars().last() {
+            self.last_char = Some(last);
+        }
+        Ok(())
+    }
+
+    /// Return the exit status for the result of the program, reporting its error
+    fn finish(&mut self, result: Step) -> Option<u8> {
+        match result {
+            Ok(()) => Some(0),
+            Err(Stop::Bye(status)) => Some((status & 0xff) as u8),
+            Err(Stop::Error(message)) => self.print(&format!("{}\n", message)).ok().map(|_| 1),
+            Err(Stop::Output) => None,
+        }
+    }
+
+    fn save_stack(&self, path: &str) -> bool {
+        let items: Vec<String> = self.stack.iter().map(i16::to_string).collect();
+        std::fs::write(path, items.join(" ")).is_ok()
+    }
+}
+
+fn main() -> ExitCode {
+    let mut machine = Machine::new();
+    let result = program(&mut machine);
+    let status = machine.finish(result);
+    let flushed = machine.output.flush().is_ok();
+    if STACK_FILE.is_some_and(|path| !machine.save_stack(path)) {
+        print!("{}", CAN_NOT_WRITE_FILE);
+        return ExitCode::from(RUNNER_ERROR_STATUS);
+    }
+    match status {
+        Some(status) if flushed => ExitCode::from(status),
+        _ => {
+            print!("{}", CAN_NOT_WRITE_TO_OUTPUT);
+            ExitCode::from(RUNNER_ERROR_STATUS)
+        }
+    }
+}
@@ -0,0 +1,154 @@
+pub mod code;
+pub mod compiler;
+pub mod instruction;
+pub mod optimizer;
+
+use crate::{context::BorthContext, errors::*, position::BorthPosition, step::BorthStep};
+use code::BorthCode;
+use instruction::BorthInstruction;
+use std::rc::Rc;
+
+/// What the machine does after an instruction
+enum BorthFlow {
+    Next,
+    Jump(usize),
+    Call(Rc<BorthCode>),
+    Return,
+}
+
+/// Run the code of a word in a loop, keeping the callers in an explicit return stack
+/// instead of the native one. On error, the call frames are left in the context.
+pub fn call(ctx: &mut BorthContext, name: &Rc<str>, code: &Rc<BorthCode>) -> BorthResult<()> {
+    ctx.push_frame(name);
+    let mut returns: Vec<(Rc<BorthCode>, usize, usize)> = vec![];
+    let mut code = Rc::clone(code);
+    let (mut pc, mut entry_depth) = (0, ctx.stack_items().len());
+    loop {
+        let (instruction, position) = code.get(pc).ok_or(BorthError::RuntimeError)?;
+        let flow = check_effect(ctx, &code, entry_depth, instruction)
+            .and_then(|_| match BorthStep::instruction(position, instruction) {
+                Some(step) => ctx.run_hook(&step),
+                None => Ok(()),
+            })
+            .and_then(|_| execute(ctx, instruction, position))
+            .inspect_err(|_| ctx.locate_error(position))?;
+        pc += 1;
+        match flow {
+            BorthFlow::Next => {}
+            BorthFlow::Jump(target) => pc = target,
+            BorthFlow::Call(callee) => {
+                returns.push((code, pc, entry_depth));
+                (code, pc, entry_depth) = (callee, 0, ctx.stack_items().len());
+            }
+            BorthFlow::Return => match returns.pop() {
+                Some(caller) => (code, pc, entry_depth) = caller,
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+/// Fail with `StackEffectMismatch` if the code is about to return after changing
+/// the depth of the stack in another way than its declared effect says
+fn check_effect(
+    ctx: &BorthContext,
+    code: &BorthCode,
+    entry_depth: usize,
+    instruction: &BorthInstruction,
+) -> BorthResult<()> {
+    let (BorthInstruction::Return, Some(effect)) = (instruction, code.effect()) else {
+        return Ok(());
+    };
+    let change = ctx.stack_items().len() as isize - entry_depth as isize;
+    if change == effect.depth_change() {
+        return Ok(());
+    }
+    let name = ctx
+        .frames()
+        .last()
+        .map(|name| name.to_string())
+        .unwrap_or_default();
+    Err(BorthError::StackEffectMismatch(name))
+}
+
+/// Execute a single instruction found at the position.
+/// Every instruction but jumps and returns takes a step.
+fn execute(
+    ctx: &mut BorthContext,
+    instruction: &BorthInstruction,
+    position: BorthPosition,
+) -> BorthResult<BorthFlow> {
+    match instruction {
+        BorthInstruction::Push(value) => {
+            ctx.consume_step()?;
+            ctx.push_value(*value)?;
+        }
+        BorthInstruction::Operation(cb) => {
+            ctx.consume_step()?;
+            cb(ctx)?;
+        }
+        BorthInstruction::Eval(expression) => expression.eval(ctx, position)?,
+        BorthInstruction::Call(name, code) => {
+            ctx.consume_step()?;
+            ctx.push_frame(name);
+            return Ok(BorthFlow::Call(Rc::clone(code)));
+        }
+        BorthInstruction::BranchIfZero(target) => {
+            ctx.consume_step()?;
+            if ctx.pop_value()? == 0 {
+                return Ok(BorthFlow::Jump(*target));
+            }
+        }
+        BorthInstruction::Jump(target) => return Ok(BorthFlow::Jump(*target)),
+        BorthInstruction::Return => {
+            ctx.pop_frame();
+            return Ok(BorthFlow::Return);
+        }
+    }
+    Ok(BorthFlow::Next)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BorthError, BorthInterpreter};
+
+    #[test]
+    fn test1_deep_nesting_does_not_use_the_native_stack() {
+        let mut interpreter = BorthInterpreter::with_stack_size(20);
+        let mut code = String::from(": w0 1 ;\n");
+        for i in 1..100_000 {
+            code += &format!(": w{} w{} ;\n", i, i - 1);
+        }
+        code += "w99999";
+        assert_eq!(interpreter.eval(&code), Ok(()));
+        assert_eq!(interpreter.stack_items(), &[1]);
+    }
+
+    #[test]
+    fn test2_branches() {
+        let mut interpreter = BorthInterpreter::with_stack_size(20);
+        let code = ": sign dup 0 < if drop -1 else 0 > if 1 else 0 then then ;";
+        assert_eq!(interpreter.eval(code), Ok(()));
+        assert_eq!(interpreter.eval("-5 sign 0 sign 7 sign"), Ok(()));
+        assert_eq!(interpreter.stack_items(), &[-1, 0, 1]);
+    }
+
+    #[test]
+    fn test3_error_keeps_frames_and_budget() {
+        let mut interpreter = BorthInterpreter::with_stack_size(20);
+        assert_eq!(interpreter.eval(": inner + ; : outer 1 inner ;"), Ok(()));
+        interpreter.set_max_steps(Some(3));
+        assert_eq!(interpreter.eval("outer"), Err(BorthError::BudgetExhausted));
+        assert_eq!(interpreter.stack_items(), &[1]);
+        interpreter.set_max_steps(None);
+        assert_eq!(interpreter.pop(), Ok(1));
+        interpreter.run_code("outer");
+        assert_eq!(interpreter.last_error(), Some(&BorthError::StackUnderflow));
+        assert_eq!(interpreter.last_error_word(), Some("inner"));
+        assert!(
+            interpreter
+                .last_error_position()
+                .is_some_and(|p| p.column == 9)
+        );
+    }
+}
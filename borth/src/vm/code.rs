@@ -0,0 +1,104 @@
+use crate::{
+    effect::BorthEffect, expression::BorthExpression, position::BorthPosition,
+    vm::instruction::BorthInstruction,
+};
+use std::rc::Rc;
+
+#[derive(Debug, Default)]
+/// Flat array of instructions compiled from a word body,
+/// with the position in the code of each one
+pub struct BorthCode {
+    instructions: Vec<BorthInstruction>,
+    positions: Vec<BorthPosition>,
+    effect: Option<BorthEffect>,
+}
+
+impl BorthCode {
+    /// Add an instruction at the end and return its index
+    pub fn push(&mut self, position: BorthPosition, instruction: BorthInstruction) -> usize {
+        self.instructions.push(instruction);
+        self.positions.push(position);
+        self.instructions.len() - 1
+    }
+
+    /// Replace the instruction at the index, if there is one
+    pub fn patch(&mut self, index: usize, instruction: BorthInstruction) {
+        if let Some(old) = self.instructions.get_mut(index) {
+            *old = instruction;
+        }
+    }
+
+    /// Remove the last instruction and return it
+    pub fn pop(&mut self) -> Option<BorthInstruction> {
+        self.positions.pop();
+        self.instructions.pop()
+    }
+
+    /// Return the last instruction
+    pub fn last(&self) -> Option<&BorthInstruction> {
+        self.instructions.last()
+    }
+
+    /// Replace the target of every branch and jump with the one given by `map`
+    pub fn map_targets(&mut self, map: impl Fn(usize) -> usize) {
+        for instruction in self.instructions.iter_mut() {
+            match instruction {
+                BorthInstruction::BranchIfZero(target) | BorthInstruction::Jump(target) => {
+                    *target = map(*target)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Check at runtime that running the code changes the depth of the stack
+    /// like the effect says
+    pub fn set_effect(&mut self, effect: Option<BorthEffect>) {
+        self.effect = effect;
+    }
+
+    /// Return the effect checked when the code returns, if any
+    pub fn effect(&self) -> Option<BorthEffect> {
+        self.effect
+    }
+
+    /// Return the instructions in order
+    pub fn instructions(&self) -> &[BorthInstruction] {
+        &self.instructions
+    }
+
+    /// Return the index the next instruction will take
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    /// Return the instruction at the index and its position
+    pub fn get(&self, index: usize) -> Option<(&BorthInstruction, BorthPosition)> {
+        let instruction = self.instructions.get(index)?;
+        let position = self.positions.get(index).copied().unwrap_or_default();
+        Some((instruction, position))
+    }
+
+    /// Move the shared expressions and code the instructions hold to the given lists,
+    /// so they can be dropped one at a time instead of recursively
+    pub fn release(
+        self,
+        expressions: &mut Vec<Rc<BorthExpression>>,
+        codes: &mut Vec<Rc<BorthCode>>,
+    ) {
+        for instruction in self.instructions {
+            match instruction {
+                BorthInstruction::Call(_, code) => codes.push(code),
+                BorthInstruction::Eval(expression) => expressions.push(expression),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Code is compared by its instructions, positions are left aside
+impl PartialEq for BorthCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.instructions == other.instructions
+    }
+}
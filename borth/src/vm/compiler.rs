@@ -0,0 +1,122 @@
+use crate::{
+    expression::BorthExpression,
+    node::BorthNode,
+    position::BorthPosition,
+    vm::{code::BorthCode, instruction::BorthInstruction},
+};
+use std::rc::Rc;
+
+/// Compile a word body to flat code ending with `Return`, placed at the last node.
+/// Called words are referenced by their code, so later redefinitions don't change it.
+pub fn compile(body: &[BorthNode]) -> BorthCode {
+    let mut code = BorthCode::default();
+    compile_nodes(&mut code, body);
+    let end = body.last().map(BorthNode::position).unwrap_or_default();
+    code.push(end, BorthInstruction::Return);
+    code
+}
+
+fn compile_nodes(code: &mut BorthCode, nodes: &[BorthNode]) {
+    for node in nodes {
+        compile_node(code, node);
+    }
+}
+
+fn compile_node(code: &mut BorthCode, node: &BorthNode) {
+    let position = node.position();
+    let instruction = match node.expression() {
+        BorthExpression::Number(value) => BorthInstruction::Push(*value),
+        BorthExpression::Operation(cb) => BorthInstruction::Operation(*cb),
+        BorthExpression::Word(name, _, word_code) => {
+            BorthInstruction::Call(Rc::clone(name), Rc::clone(word_code))
+        }
+        BorthExpression::IfElseThen(if_block, else_block) => {
+            return compile_if(code, position, if_block, else_block);
+        }
+        _ => BorthInstruction::Eval(node.shared_expression()),
+    };
+    code.push(position, instruction);
+}
+
+/// Compile `if` to a branch over the first block and a jump over the second one
+fn compile_if(
+    code: &mut BorthCode,
+    position: BorthPosition,
+    if_block: &[BorthNode],
+    else_block: &[BorthNode],
+) {
+    let branch = code.push(position, BorthInstruction::BranchIfZero(0));
+    compile_nodes(code, if_block);
+    let jump = code.push(position, BorthInstruction::Jump(0));
+    code.patch(branch, BorthInstruction::BranchIfZero(code.len()));
+    compile_nodes(code, else_block);
+    code.patch(jump, BorthInstruction::Jump(code.len()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::arithmetic::add;
+
+    fn expected_code(instructions: Vec<BorthInstruction>) -> BorthCode {
+        let mut code = BorthCode::default();
+        for instruction in instructions {
+            code.push(BorthPosition::default(), instruction);
+        }
+        code
+    }
+
+    #[test]
+    fn test1_compile_straight_body() {
+        let body = vec![
+            BorthNode::unlocated(BorthExpression::Number(1)),
+            BorthNode::unlocated(BorthExpression::Operation(add::call)),
+            BorthNode::unlocated(BorthExpression::DotQuote("hi".into())),
+        ];
+        let expected = expected_code(vec![
+            BorthInstruction::Push(1),
+            BorthInstruction::Operation(add::call),
+            BorthInstruction::Eval(Rc::new(BorthExpression::DotQuote("hi".into()))),
+            BorthInstruction::Return,
+        ]);
+        assert_eq!(compile(&body), expected);
+    }
+
+    #[test]
+    fn test2_compile_if_else_then() {
+        let body = vec![BorthNode::unlocated(BorthExpression::IfElseThen(
+            vec![BorthNode::unlocated(BorthExpression::Number(1))],
+            vec![
+                BorthNode::unlocated(BorthExpression::Number(2)),
+                BorthNode::unlocated(BorthExpression::Number(3)),
+            ],
+        ))];
+        let expected = expected_code(vec![
+            BorthInstruction::BranchIfZero(3),
+            BorthInstruction::Push(1),
+            BorthInstruction::Jump(5),
+            BorthInstruction::Push(2),
+            BorthInstruction::Push(3),
+            BorthInstruction::Return,
+        ]);
+        assert_eq!(compile(&body), expected);
+    }
+
+    #[test]
+    fn test3_compile_call() {
+        let inner = BorthExpression::word(
+            "inner",
+            vec![BorthNode::unlocated(BorthExpression::Number(1))],
+        );
+        let inner_code = match &inner {
+            BorthExpression::Word(_, _, code) => Rc::clone(code),
+            _ => Rc::default(),
+        };
+        let body = vec![BorthNode::unlocated(inner)];
+        let expected = expected_code(vec![
+            BorthInstruction::Call("inner".into(), inner_code),
+            BorthInstruction::Return,
+        ]);
+        assert_eq!(compile(&body), expected);
+    }
+}
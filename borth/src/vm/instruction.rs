@@ -0,0 +1,26 @@
+use crate::{
+    context::BorthItem,
+    expression::{BorthExpression, BorthOperation},
+    vm::code::BorthCode,
+};
+use std::rc::Rc;
+
+#[allow(unpredictable_function_pointer_comparisons)]
+#[derive(Debug, Clone, PartialEq)]
+/// Instructions of the compiled code of a word
+pub enum BorthInstruction {
+    /// Push a number to the stack
+    Push(BorthItem),
+    /// Run a builtin operation
+    Operation(BorthOperation),
+    /// Eval an expression that has no instruction of its own
+    Eval(Rc<BorthExpression>),
+    /// Call the code of a word, coming back to the next instruction when it returns
+    Call(Rc<str>, Rc<BorthCode>),
+    /// Pop a flag and go to the instruction at the target if it is zero
+    BranchIfZero(usize),
+    /// Go to the instruction at the target
+    Jump(usize),
+    /// Go back to the caller
+    Return,
+}
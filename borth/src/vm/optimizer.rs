@@ -0,0 +1,371 @@
+use crate::{
+    context::{BorthContext, BorthItem},
+    expression::{BorthOperation, arithmetic::*, booleans::*, stack::*},
+    position::BorthPosition,
+    vm::{code::BorthCode, instruction::BorthInstruction},
+};
+use std::collections::HashSet;
+
+/// Words with up to this many instructions are inlined
+const INLINE_LIMIT: usize = 4;
+
+/// Builtin operations that only work on the stack, so they can run while optimizing
+const PURE_OPERATIONS: [BorthOperation; 15] = [
+    add::call,
+    sub::call,
+    mul::call,
+    div::call,
+    dup::call,
+    drop::call,
+    swap::call,
+    over::call,
+    rot::call,
+    eq::call,
+    lt::call,
+    gt::call,
+    and::call,
+    or::call,
+    not::call,
+];
+
+/// Arithmetic on two values that returns `None` on overflow or division by zero
+type BorthCheckedOperation = fn(BorthItem, BorthItem) -> Option<BorthItem>;
+
+/// Arithmetic operations with their checked versions, to leave unfolded the ones
+/// that would overflow
+const CHECKED_ARITHMETIC: [(BorthOperation, BorthCheckedOperation); 4] = [
+    (add::call, BorthItem::checked_add),
+    (sub::call, BorthItem::checked_sub),
+    (mul::call, BorthItem::checked_mul),
+    (div::call, BorthItem::checked_div),
+];
+
+/// Pairs of operations that undo each other when they are next to each other
+const CANCELLING_PAIRS: [(BorthOperation, BorthOperation); 2] =
+    [(swap::call, swap::call), (dup::call, drop::call)];
+
+/// Optimize compiled code: inline calls to tiny straight words, run pure operations on
+/// constants (`2 3 +` becomes `5`) and drop pairs like `swap swap` or `dup drop`.
+/// Inlined words are taken from the code that was called, so redefinitions still
+/// don't change it. The optimized code takes fewer steps, may not report the
+/// underflow of a removed pair and doesn't leave call frames for inlined words.
+pub fn optimize(code: &BorthCode) -> BorthCode {
+    let targets = branch_targets(code);
+    let mut optimized = BorthCode::default();
+    let mut map = Vec::with_capacity(code.len() + 1);
+    let mut block_start = 0;
+    for index in 0..code.len() {
+        if targets.contains(&index) {
+            block_start = optimized.len();
+        }
+        map.push(optimized.len());
+        if let Some((instruction, position)) = code.get(index) {
+            emit_inlined(&mut optimized, block_start, instruction, position);
+        }
+    }
+    map.push(optimized.len());
+    optimized.map_targets(|target| map.get(target).copied().unwrap_or(target));
+    optimized
+}
+
+/// Return the indexes some branch or jump goes to
+fn branch_targets(code: &BorthCode) -> HashSet<usize> {
+    code.instructions()
+        .iter()
+        .filter_map(|instruction| match instruction {
+            BorthInstruction::BranchIfZero(target) | BorthInstruction::Jump(target) => {
+                Some(*target)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Emit the instruction, or the body of the word it calls if it is tiny and straight
+fn emit_inlined(
+    code: &mut BorthCode,
+    block_start: usize,
+    instruction: &BorthInstruction,
+    position: BorthPosition,
+) {
+    if let BorthInstruction::Call(_, callee) = instruction
+        && let Some(body) = inlinable_body(callee)
+    {
+        for instruction in body {
+            emit(code, block_start, instruction, position);
+        }
+        return;
+    }
+    emit(code, block_start, instruction, position);
+}
+
+/// Return the instructions before `Return` if they are few, have no branches
+/// and no effect to check when they return
+fn inlinable_body(code: &BorthCode) -> Option<&[BorthInstruction]> {
+    let (body, last) = code.instructions().split_at(code.len().checked_sub(1)?);
+    let straight = body.iter().all(|instruction| {
+        !matches!(
+            instruction,
+            BorthInstruction::BranchIfZero(_)
+                | BorthInstruction::Jump(_)
+                | BorthInstruction::Return
+        )
+    });
+    let returns = last == [BorthInstruction::Return] && code.effect().is_none();
+    (returns && straight && body.len() <= INLINE_LIMIT).then_some(body)
+}
+
+/// Emit the instruction, folding it with the previous ones of the same block
+fn emit(
+    code: &mut BorthCode,
+    block_start: usize,
+    instruction: &BorthInstruction,
+    position: BorthPosition,
+) {
+    if let BorthInstruction::Operation(operation) = instruction {
+        if cancels_last(code, block_start, *operation) {
+            code.pop();
+            return;
+        }
+        if fold(code, block_start, *operation, position) {
+            return;
+        }
+    }
+    code.push(position, instruction.clone());
+}
+
+/// Return true if the operation undoes the last one emitted in the block
+fn cancels_last(code: &BorthCode, block_start: usize, operation: BorthOperation) -> bool {
+    let last = match code.last() {
+        Some(BorthInstruction::Operation(last)) if code.len() > block_start => *last,
+        _ => return false,
+    };
+    CANCELLING_PAIRS.iter().any(|(first, second)| {
+        std::ptr::fn_addr_eq(*first, last) && std::ptr::fn_addr_eq(*second, operation)
+    })
+}
+
+/// Run a pure operation on the constants pushed at the end of the block and replace
+/// them with the result. Return false if it can't, like when it needs other values.
+fn fold(
+    code: &mut BorthCode,
+    block_start: usize,
+    operation: BorthOperation,
+    position: BorthPosition,
+) -> bool {
+    let pure = PURE_OPERATIONS
+        .iter()
+        .any(|pure| std::ptr::fn_addr_eq(*pure, operation));
+    let constants = trailing_constants(code, block_start);
+    if !pure || constants.is_empty() || overflows(operation, &constants) {
+        return false;
+    }
+    let mut ctx = BorthContext::with_stack_size((constants.len() + 1) * 4);
+    if constants
+        .iter()
+        .try_for_each(|value| ctx.push_value(*value))
+        .is_err()
+        || operation(&mut ctx).is_err()
+    {
+        return false;
+    }
+    for _ in &constants {
+        code.pop();
+    }
+    for value in ctx.stack_items() {
+        code.push(position, BorthInstruction::Push(*value));
+    }
+    true
+}
+
+/// Return true if the operation is arithmetic and overflows or divides by zero on
+/// the last two constants
+fn overflows(operation: BorthOperation, constants: &[BorthItem]) -> bool {
+    let Some((_, checked)) = CHECKED_ARITHMETIC
+        .iter()
+        .find(|(arithmetic, _)| std::ptr::fn_addr_eq(*arithmetic, operation))
+    else {
+        return false;
+    };
+    match constants {
+        [.., value2, value1] => checked(*value2, *value1).is_none(),
+        _ => false,
+    }
+}
+
+/// Return the values of the `Push` instructions at the end of the block, in order
+fn trailing_constants(code: &BorthCode, block_start: usize) -> Vec<BorthItem> {
+    let block = code.instructions().get(block_start..).unwrap_or_default();
+    let mut constants: Vec<_> = block
+        .iter()
+        .rev()
+        .map_while(|instruction| match instruction {
+            BorthInstruction::Push(value) => Some(*value),
+            _ => None,
+        })
+        .collect();
+    constants.reverse();
+    constants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BorthInterpreter, expression::output::dot};
+    use std::rc::Rc;
+
+    fn code_of(instructions: Vec<BorthInstruction>) -> BorthCode {
+        let mut code = BorthCode::default();
+        for instruction in instructions {
+            code.push(BorthPosition::default(), instruction);
+        }
+        code
+    }
+
+    fn create_interpreter() -> BorthInterpreter {
+        let mut interpreter = BorthInterpreter::with_stack_size(20);
+        interpreter.enable_optimizer();
+        interpreter
+    }
+
+    #[test]
+    fn test1_fold_constants() {
+        let code = code_of(vec![
+            BorthInstruction::Push(2),
+            BorthInstruction::Push(3),
+            BorthInstruction::Operation(add::call),
+            BorthInstruction::Push(4),
+            BorthInstruction::Operation(mul::call),
+            BorthInstruction::Operation(dot::call),
+            BorthInstruction::Return,
+        ]);
+        let expected = code_of(vec![
+            BorthInstruction::Push(20),
+            BorthInstruction::Operation(dot::call),
+            BorthInstruction::Return,
+        ]);
+        assert_eq!(optimize(&code), expected);
+    }
+
+    #[test]
+    fn test2_keep_failing_operations() {
+        let code = code_of(vec![
+            BorthInstruction::Push(1),
+            BorthInstruction::Push(0),
+            BorthInstruction::Operation(div::call),
+            BorthInstruction::Push(1),
+            BorthInstruction::Operation(add::call),
+            BorthInstruction::Return,
+        ]);
+        assert_eq!(optimize(&code), code);
+    }
+
+    #[test]
+    fn test3_remove_cancelling_pairs() {
+        let code = code_of(vec![
+            BorthInstruction::Operation(swap::call),
+            BorthInstruction::Operation(dup::call),
+            BorthInstruction::Operation(drop::call),
+            BorthInstruction::Operation(swap::call),
+            BorthInstruction::Operation(over::call),
+            BorthInstruction::Return,
+        ]);
+        let expected = code_of(vec![
+            BorthInstruction::Operation(over::call),
+            BorthInstruction::Return,
+        ]);
+        assert_eq!(optimize(&code), expected);
+    }
+
+    #[test]
+    fn test4_do_not_fold_across_branch_targets() {
+        let code = code_of(vec![
+            BorthInstruction::Push(1),
+            BorthInstruction::BranchIfZero(4),
+            BorthInstruction::Push(2),
+            BorthInstruction::Push(3),
+            BorthInstruction::Operation(add::call),
+            BorthInstruction::Push(4),
+            BorthInstruction::Operation(dup::call),
+            BorthInstruction::Return,
+        ]);
+        let expected = code_of(vec![
+            BorthInstruction::Push(1),
+            BorthInstruction::BranchIfZero(4),
+            BorthInstruction::Push(2),
+            BorthInstruction::Push(3),
+            BorthInstruction::Operation(add::call),
+            BorthInstruction::Push(4),
+            BorthInstruction::Push(4),
+            BorthInstruction::Return,
+        ]);
+        assert_eq!(optimize(&code), expected);
+    }
+
+    #[test]
+    fn test5_inline_tiny_words() {
+        let negate = code_of(vec![
+            BorthInstruction::Push(-1),
+            BorthInstruction::Operation(mul::call),
+            BorthInstruction::Return,
+        ]);
+        let code = code_of(vec![
+            BorthInstruction::Push(5),
+            BorthInstruction::Call("negate".into(), Rc::new(negate)),
+            BorthInstruction::Return,
+        ]);
+        let expected = code_of(vec![BorthInstruction::Push(-5), BorthInstruction::Return]);
+        assert_eq!(optimize(&code), expected);
+    }
+
+    #[test]
+    fn test6_same_results_as_without_optimizer() {
+        let code = ": negate -1 * ; : even? dup 2 / 2 * = ; : abs dup 0 < if negate then ;
+            -7 abs 6 even? 7 even? 2 3 + swap swap";
+        let mut interpreter = create_interpreter();
+        assert_eq!(interpreter.eval(code), Ok(()));
+        assert_eq!(interpreter.stack_items(), &[7, -1, 0, 5]);
+    }
+
+    #[test]
+    fn test7_non_transitive() {
+        let mut interpreter = create_interpreter();
+        assert_eq!(
+            interpreter.eval(": foo 5 ; : bar foo ; : foo 6 ; bar foo"),
+            Ok(())
+        );
+        assert_eq!(interpreter.stack_items(), &[5, 6]);
+    }
+
+    #[test]
+    fn test8_keep_runtime_errors() {
+        let mut interpreter = create_interpreter();
+        assert_eq!(interpreter.eval(": f 1 0 / ;"), Ok(()));
+        assert_eq!(
+            interpreter.eval("f"),
+            Err(crate::BorthError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn test9_keep_overflowing_operations() {
+        let code = code_of(vec![
+            BorthInstruction::Push(32767),
+            BorthInstruction::Push(1),
+            BorthInstruction::Operation(add::call),
+            BorthInstruction::Return,
+        ]);
+        assert_eq!(optimize(&code), code);
+        let code = code_of(vec![
+            BorthInstruction::Push(-32768),
+            BorthInstruction::Push(-1),
+            BorthInstruction::Operation(div::call),
+            BorthInstruction::Return,
+        ]);
+        assert_eq!(optimize(&code), code);
+
+        let mut interpreter = create_interpreter();
+        interpreter.run_code(": f 32767 1 + ; : g -32768 -1 / ;");
+        assert_eq!(interpreter.last_error(), None);
+    }
+}
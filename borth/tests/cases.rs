@@ -0,0 +1,85 @@
+//! Runs every case file in `cases/` through the interpreter and reports the
+//! cases whose output or stack differ from the expected ones.
+
+mod yaml;
+
+use borth::BorthInterpreter;
+use std::{fs, path::Path};
+use yaml::BorthCase;
+
+const CASES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/cases");
+
+/// Run a case with a new interpreter and describe how it failed, if it did
+fn run_case(case: &BorthCase, optimize: bool) -> Option<String> {
+    let mut interpreter = match case.stack_size {
+        Some(stack_size) => BorthInterpreter::with_stack_size(stack_size),
+        None => BorthInterpreter::new(),
+    };
+    if optimize {
+        interpreter.enable_optimizer();
+    }
+    let (stack, output) = interpreter.run_code(&case.code);
+    let mut diff = String::new();
+    if output != case.expected_output {
+        diff += &format!(
+            "  output: expected {:?}, got {:?}\n",
+            case.expected_output, output
+        );
+    }
+    if stack != case.expected_stack {
+        diff += &format!(
+            "  stack: expected {:?}, got {:?}\n",
+            case.expected_stack, stack
+        );
+    }
+    (!diff.is_empty()).then_some(diff)
+}
+
+/// Run every case in a file and return the report of the failed ones
+fn run_file(path: &Path, optimize: bool) -> Result<String, String> {
+    let name = path.display();
+    let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", name, err))?;
+    let cases = yaml::parse_cases(&text).map_err(|err| format!("{}: {}", name, err))?;
+    if cases.is_empty() {
+        return Err(format!("{}: no cases found", name));
+    }
+    let report = cases
+        .iter()
+        .filter_map(|case| {
+            run_case(case, optimize).map(|diff| format!("{}: {}\n{}", name, case.name, diff))
+        })
+        .collect();
+    Ok(report)
+}
+
+/// Return the case files, sorted by name
+fn case_files() -> Result<Vec<std::path::PathBuf>, String> {
+    let entries = fs::read_dir(CASES_DIR).map_err(|err| format!("{}: {}", CASES_DIR, err))?;
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "yaml"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+fn run_cases(optimize: bool) -> Result<(), String> {
+    let files = case_files()?;
+    assert!(!files.is_empty(), "no case files in {}", CASES_DIR);
+    let mut report = String::new();
+    for path in files {
+        report += &run_file(&path, optimize)?;
+    }
+    assert!(report.is_empty(), "failed cases:\n{}", report);
+    Ok(())
+}
+
+#[test]
+fn test_cases() -> Result<(), String> {
+    run_cases(false)
+}
+
+#[test]
+fn test_cases_optimized() -> Result<(), String> {
+    run_cases(true)
+}
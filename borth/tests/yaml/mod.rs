@@ -0,0 +1,158 @@
+//! Parser for the subset of YAML used by the case files: a list of maps with
+//! quoted strings, integers, flow lists of integers and literal `|` blocks.
+
+use borth::BorthItem;
+
+/// A test case read from a case file
+#[derive(Debug, Default, PartialEq)]
+pub struct BorthCase {
+    pub name: String,
+    pub code: String,
+    pub expected_output: String,
+    pub expected_stack: Vec<BorthItem>,
+    pub stack_size: Option<usize>,
+}
+
+/// Parse every case in the given text, or return a message with the failing line
+pub fn parse_cases(text: &str) -> Result<Vec<BorthCase>, String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut cases: Vec<BorthCase> = vec![];
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        index += 1;
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let entry = match trimmed.strip_prefix("- ") {
+            Some(entry) => {
+                cases.push(BorthCase::default());
+                entry
+            }
+            None => trimmed,
+        };
+        let case = cases
+            .last_mut()
+            .ok_or(format!("line {}: key outside a case", index))?;
+        let mut block = String::new();
+        if entry.ends_with('|') {
+            block = read_block(&lines, &mut index, line.len() - entry.len());
+        }
+        set_field(case, entry, block).map_err(|err| format!("line {}: {}", index, err))?;
+    }
+    Ok(cases)
+}
+
+/// Read the lines indented deeper than `indent`, as the content of a `|` block
+fn read_block(lines: &[&str], index: &mut usize, indent: usize) -> String {
+    let start = *index;
+    while *index < lines.len() {
+        let line = lines[*index];
+        let deeper = line.len() - line.trim_start().len() > indent;
+        if !line.trim().is_empty() && !deeper {
+            break;
+        }
+        *index += 1;
+    }
+    let block = &lines[start..*index];
+    let margin = block
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut text: String = block
+        .iter()
+        .map(|line| format!("{}\n", line.get(margin..).unwrap_or("")))
+        .collect();
+    text.truncate(text.trim_end().len());
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text
+}
+
+/// Set the field named by a `key: value` entry, taking `block` for a `|` value
+fn set_field(case: &mut BorthCase, entry: &str, block: String) -> Result<(), String> {
+    let (key, value) = entry
+        .split_once(':')
+        .ok_or(format!("expected `key: value`, found `{}`", entry))?;
+    let value = value.trim();
+    match key {
+        "name" => case.name = parse_string(value)?,
+        "code" if value == "|" => case.code = block,
+        "code" => case.code = parse_string(value)?,
+        "expected_output" => case.expected_output = parse_string(value)?,
+        "expected_stack" => case.expected_stack = parse_list(value)?,
+        "stack_size" => case.stack_size = Some(parse_number(value)?),
+        _ => return Err(format!("unknown key `{}`", key)),
+    }
+    Ok(())
+}
+
+/// Parse a double quoted string, with `\n`, `\t`, `\"` and `\\` escapes
+fn parse_string(value: &str) -> Result<String, String> {
+    let inner = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or(format!("expected a quoted string, found `{}`", value))?;
+    let mut string = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            string.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => string.push('\n'),
+            Some('t') => string.push('\t'),
+            Some(c @ ('"' | '\\')) => string.push(c),
+            other => return Err(format!("invalid escape `\\{}`", other.unwrap_or(' '))),
+        }
+    }
+    Ok(string)
+}
+
+/// Parse a flow list of numbers, like `[1, -2, 3]`
+fn parse_list(value: &str) -> Result<Vec<BorthItem>, String> {
+    let inner = value
+        .strip_prefix('[')
+        .and_then(|value| value.strip_suffix(']'))
+        .ok_or(format!("expected a list, found `{}`", value))?;
+    inner
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(parse_number)
+        .collect()
+}
+
+/// Parse an integer of the type the caller needs
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .or(Err(format!("expected a number, found `{}`", value)))
+}
+
+#[test]
+fn test_parse_case() {
+    let text = "# comment\n- name: \"a \\\"case\\\"\"\n  stack_size: 10\n  code: |\n    1 2\n\n    .\n  expected_output: \"2\\n\"\n  expected_stack: [1, -3]\n";
+    let expected = BorthCase {
+        name: "a \"case\"".into(),
+        code: "1 2\n\n.\n".into(),
+        expected_output: "2\n".into(),
+        expected_stack: vec![1, -3],
+        stack_size: Some(10),
+    };
+    assert_eq!(parse_cases(text), Ok(vec![expected]));
+}
+
+#[test]
+fn test_reject_unknown_key() {
+    let text = "- name: \"case\"\n  expected: []\n";
+    assert_eq!(
+        parse_cases(text),
+        Err("line 2: unknown key `expected`".to_string())
+    );
+}